@@ -71,6 +71,21 @@ pub fn king_attacks(sq: Square) -> Bitboard {
     unsafe { Bitboard(*KING_ATTACKS.get_unchecked(sq.index() as usize)) }
 }
 
+/// Looks up attacks for a ferz (one diagonal step, as in Shatranj) on `sq`.
+#[inline]
+pub fn ferz_attacks(sq: Square) -> Bitboard {
+    // This is safe because properly constructed squares are in bounds.
+    unsafe { Bitboard(*FERZ_ATTACKS.get_unchecked(sq.index() as usize)) }
+}
+
+/// Looks up attacks for an alfil (two square diagonal leap, as in Shatranj)
+/// on `sq`.
+#[inline]
+pub fn alfil_attacks(sq: Square) -> Bitboard {
+    // This is safe because properly constructed squares are in bounds.
+    unsafe { Bitboard(*ALFIL_ATTACKS.get_unchecked(sq.index() as usize)) }
+}
+
 /// Looks up attacks for a rook on `sq` with `occupied` squares.
 #[inline]
 pub fn rook_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
@@ -183,4 +198,11 @@ mod tests {
         assert_eq!(rook_attacks(square::D6, Bitboard(0x3f7f28802826f5b9)),
                    Bitboard(0x8370808000000));
     }
+
+    #[test]
+    fn test_leaper_attacks() {
+        assert_eq!(ferz_attacks(square::A1), Bitboard::from_square(square::B2));
+        assert_eq!(alfil_attacks(square::C1),
+                   Bitboard::from_square(square::A3).with(square::E3));
+    }
 }
@@ -0,0 +1,380 @@
+// This file is part of the shakmaty library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Fairy pieces defined in Betza notation.
+//!
+//! Supported atoms are the leapers `W`, `F`, `D`, `N`, `A`, `H`, `C`, `Z`,
+//! `G` and the shorthands `K`, `Q`, `R` and `B`. An atom can be turned into
+//! a rider by doubling it (`NN`) or limited to a range with a number (`W2`).
+//!
+//! Atoms can be prefixed with the modifiers `m` (move only), `c` (capture
+//! only) and the directions `f`, `b`, `l`, `r` (forward, backward, left,
+//! right), `v` (vertical) and `s` (sideways), relative to the side of the
+//! piece.
+//!
+//! # Examples
+//!
+//! ```
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! use shakmaty::{Bitboard, Color};
+//! use shakmaty::square;
+//! use shakmaty::betza::Betza;
+//!
+//! // The archbishop combines the moves of a bishop and a knight.
+//! let archbishop: Betza = "BN".parse()?;
+//!
+//! let attacks = archbishop.attacks(Color::White, square::A1, Bitboard(0));
+//! assert!(attacks.contains(square::H8));
+//! assert!(attacks.contains(square::B3));
+//! assert!(!attacks.contains(square::A2));
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::error::Error;
+
+use attacks;
+use square::Square;
+use bitboard::Bitboard;
+use types::Color;
+
+/// Error when parsing invalid Betza notation.
+pub struct InvalidBetza {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidBetza {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidBetza").finish()
+    }
+}
+
+impl fmt::Display for InvalidBetza {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid betza".fmt(f)
+    }
+}
+
+impl Error for InvalidBetza {
+    fn description(&self) -> &str {
+        "invalid betza"
+    }
+}
+
+impl From<()> for InvalidBetza {
+    fn from(_: ()) -> InvalidBetza {
+        InvalidBetza { _priv: () }
+    }
+}
+
+/// A direction a rider can move in, that is not covered by the magic rook
+/// and bishop attack tables.
+#[derive(Clone, Debug)]
+struct Rider {
+    quiet: bool,
+    capture: bool,
+    white: (i8, i8),
+    range: u8,
+}
+
+/// A piece defined in Betza notation.
+///
+/// Leaper tables are generated when parsing. Riders moving like rooks or
+/// bishops reuse the magic attack tables, masked to the allowed directions.
+#[derive(Clone)]
+pub struct Betza {
+    notation: String,
+    leaps: Box<[[Bitboard; 64]; 4]>, // indexed by capture * 2 + color
+    rook_rays: Box<[[Bitboard; 64]; 4]>,
+    bishop_rays: Box<[[Bitboard; 64]; 4]>,
+    riders: Vec<Rider>,
+}
+
+impl fmt::Debug for Betza {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Betza").field(&self.notation).finish()
+    }
+}
+
+impl fmt::Display for Betza {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.notation.fmt(f)
+    }
+}
+
+impl FromStr for Betza {
+    type Err = InvalidBetza;
+
+    fn from_str(betza: &str) -> Result<Betza, InvalidBetza> {
+        Betza::from_bytes(betza.as_bytes())
+    }
+}
+
+impl Betza {
+    /// Parses a piece in Betza notation.
+    pub fn from_bytes(betza: &[u8]) -> Result<Betza, InvalidBetza> {
+        let mut piece = Betza {
+            notation: String::from_utf8(betza.to_vec()).map_err(|_| ())?,
+            leaps: Box::new([[Bitboard(0); 64]; 4]),
+            rook_rays: Box::new([[Bitboard(0); 64]; 4]),
+            bishop_rays: Box::new([[Bitboard(0); 64]; 4]),
+            riders: Vec::new(),
+        };
+
+        let mut start = 0;
+        let mut i = 0;
+
+        while i < betza.len() {
+            let ch = betza[i];
+            i += 1;
+
+            if b"mcfblrvs".contains(&ch) {
+                continue;
+            }
+
+            let modifiers = &betza[start..(i - 1)];
+
+            let (atoms, mut range) = match ch {
+                b'K' => (&b"WF"[..], 1),
+                b'Q' => (&b"WF"[..], 0),
+                b'R' => (&b"W"[..], 0),
+                b'B' => (&b"F"[..], 0),
+                b'W' | b'F' | b'D' | b'N' | b'A' | b'H' | b'C' | b'Z' | b'G' => (&betza[(i - 1)..i], 1),
+                _ => return Err(InvalidBetza { _priv: () }),
+            };
+
+            if atoms.len() == 1 && range == 1 && i < betza.len() {
+                if betza[i] == ch {
+                    range = 0;
+                    i += 1;
+                } else if betza[i].is_ascii_digit() {
+                    range = betza[i] - b'0';
+                    i += 1;
+                }
+            }
+
+            for atom in atoms {
+                piece.add_atom(*atom, range, modifiers);
+            }
+
+            start = i;
+        }
+
+        if start != betza.len() || betza.is_empty() {
+            return Err(InvalidBetza { _priv: () });
+        }
+
+        Ok(piece)
+    }
+
+    fn add_atom(&mut self, atom: u8, range: u8, modifiers: &[u8]) {
+        let (a, b) = match atom {
+            b'W' => (1, 0),
+            b'F' => (1, 1),
+            b'D' => (2, 0),
+            b'N' => (2, 1),
+            b'A' => (2, 2),
+            b'H' => (3, 0),
+            b'C' => (3, 1),
+            b'Z' => (3, 2),
+            _ => (3, 3),
+        };
+
+        let has = |m: u8| modifiers.contains(&m);
+        let quiet = !has(b'c') || has(b'm');
+        let capture = !has(b'm') || has(b'c');
+
+        let mut vectors = Vec::with_capacity(8);
+        for &(df, dr) in &[(a, b), (b, a)] {
+            for &(sf, sr) in &[(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                let vector = (df * sf, dr * sr);
+                if !vectors.contains(&vector) && direction_allowed(vector, modifiers) {
+                    vectors.push(vector);
+                }
+            }
+        }
+
+        for &(df, dr) in &vectors {
+            if range == 1 {
+                for idx in 0..64 {
+                    let sq = Square::new(idx);
+                    for color in &[Color::Black, Color::White] {
+                        if let Some(to) = Square::from_coords(sq.file() + df, sq.rank() + color.fold(dr, -dr)) {
+                            self.table_mut(Table::Leaps, quiet, capture, *color, idx, to);
+                        }
+                    }
+                }
+            } else if range == 0 && (atom == b'W' || atom == b'F') {
+                let table = if atom == b'W' { Table::RookRays } else { Table::BishopRays };
+                for idx in 0..64 {
+                    let sq = Square::new(idx);
+                    for color in &[Color::Black, Color::White] {
+                        let mut file = sq.file() + df;
+                        let mut rank = sq.rank() + color.fold(dr, -dr);
+                        while let Some(to) = Square::from_coords(file, rank) {
+                            self.table_mut(table, quiet, capture, *color, idx, to);
+                            file += df;
+                            rank += color.fold(dr, -dr);
+                        }
+                    }
+                }
+            } else {
+                self.riders.push(Rider { quiet, capture, white: (df, dr), range });
+            }
+        }
+    }
+
+    fn table_mut(&mut self, table: Table, quiet: bool, capture: bool, color: Color, idx: i8, to: Square) {
+        let tables = match table {
+            Table::Leaps => &mut self.leaps,
+            Table::RookRays => &mut self.rook_rays,
+            Table::BishopRays => &mut self.bishop_rays,
+        };
+
+        if quiet {
+            tables[color as usize][idx as usize].add(to);
+        }
+        if capture {
+            tables[2 + color as usize][idx as usize].add(to);
+        }
+    }
+
+    fn targets(&self, capture: bool, color: Color, sq: Square, occupied: Bitboard) -> Bitboard {
+        let idx = capture as usize * 2 + color as usize;
+        let sq_idx = sq.index() as usize;
+
+        let mut targets = self.leaps[idx][sq_idx] |
+                          (attacks::rook_attacks(sq, occupied) & self.rook_rays[idx][sq_idx]) |
+                          (attacks::bishop_attacks(sq, occupied) & self.bishop_rays[idx][sq_idx]);
+
+        for rider in &self.riders {
+            if (capture && !rider.capture) || (!capture && !rider.quiet) {
+                continue;
+            }
+
+            let (df, dr) = (rider.white.0, color.fold(rider.white.1, -rider.white.1));
+            let (mut file, mut rank) = (sq.file() + df, sq.rank() + dr);
+            let mut steps = 0;
+
+            while let Some(to) = Square::from_coords(file, rank) {
+                targets.add(to);
+                steps += 1;
+
+                if occupied.contains(to) || steps == rider.range {
+                    break;
+                }
+
+                file += df;
+                rank += dr;
+            }
+        }
+
+        targets
+    }
+
+    /// Squares that a piece of `color` on `sq` attacks (and could capture
+    /// on) with `occupied` squares.
+    pub fn attacks(&self, color: Color, sq: Square, occupied: Bitboard) -> Bitboard {
+        self.targets(true, color, sq, occupied)
+    }
+
+    /// Squares that a piece of `color` on `sq` could move to without
+    /// capturing, if they are empty.
+    pub fn moves(&self, color: Color, sq: Square, occupied: Bitboard) -> Bitboard {
+        self.targets(false, color, sq, occupied)
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Table {
+    Leaps,
+    RookRays,
+    BishopRays,
+}
+
+fn direction_allowed((df, dr): (i8, i8), modifiers: &[u8]) -> bool {
+    let has = |m: u8| modifiers.contains(&m);
+
+    let vertical = !(has(b'f') || has(b'b')) || (has(b'f') && dr > 0) || (has(b'b') && dr < 0);
+    let horizontal = !(has(b'l') || has(b'r')) || (has(b'l') && df < 0) || (has(b'r') && df > 0);
+    let shape = !(has(b'v') || has(b's')) ||
+                (has(b'v') && dr.abs() > df.abs()) ||
+                (has(b's') && df.abs() > dr.abs());
+
+    vertical && horizontal && shape
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use square;
+
+    #[test]
+    fn test_standard_pieces() {
+        let knight: Betza = "N".parse().expect("valid betza");
+        let queen: Betza = "Q".parse().expect("valid betza");
+        let occupied = Bitboard(0x3f7f28802826f5b9);
+
+        for sq in (0..64).map(Square::new) {
+            assert_eq!(knight.attacks(Color::White, sq, occupied), attacks::knight_attacks(sq));
+            assert_eq!(queen.attacks(Color::Black, sq, occupied), attacks::queen_attacks(sq, occupied));
+            assert_eq!(queen.moves(Color::Black, sq, occupied), attacks::queen_attacks(sq, occupied));
+        }
+    }
+
+    #[test]
+    fn test_pawn() {
+        let pawn: Betza = "mfWcfF".parse().expect("valid betza");
+        assert_eq!(pawn.moves(Color::White, square::E2, Bitboard(0)),
+                   Bitboard::from_square(square::E3));
+        assert_eq!(pawn.moves(Color::Black, square::E7, Bitboard(0)),
+                   Bitboard::from_square(square::E6));
+        assert_eq!(pawn.attacks(Color::White, square::E2, Bitboard(0)),
+                   attacks::pawn_attacks(Color::White, square::E2));
+    }
+
+    #[test]
+    fn test_riders() {
+        let nightrider: Betza = "NN".parse().expect("valid betza");
+        let attacks = nightrider.attacks(Color::White, square::A1, Bitboard::from_square(square::C2));
+        assert!(attacks.contains(square::C2));
+        assert!(!attacks.contains(square::E3));
+        assert!(attacks.contains(square::D7));
+
+        let lance: Betza = "fR".parse().expect("valid betza");
+        assert_eq!(lance.attacks(Color::White, square::A1, Bitboard(0)), Bitboard::file(0).without(square::A1));
+        assert_eq!(lance.attacks(Color::Black, square::A1, Bitboard(0)), Bitboard(0));
+
+        let short_rook: Betza = "W2".parse().expect("valid betza");
+        assert_eq!(short_rook.attacks(Color::White, square::A1, Bitboard(0)).count(), 4);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("".parse::<Betza>().is_err());
+        assert!("X".parse::<Betza>().is_err());
+        assert!("Nf".parse::<Betza>().is_err());
+    }
+}
@@ -41,7 +41,7 @@ use attacks;
 ///
 /// assert_eq!(board.piece_at(square::E8), Some(Black.king()));
 /// ```
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq, Hash)]
 pub struct Board {
     occupied: Bitboard,
     occupied_co: [Bitboard; 2], // indexed by Color
@@ -227,6 +227,99 @@ impl Board {
             (attacks::pawn_attacks(!attacker, sq) & self.pawns()))
     }
 
+    /// Pieces of either color that are the only piece between `king` and
+    /// one of the `sliders` aiming at it.
+    pub fn slider_blockers(&self, sliders: Bitboard, king: Square) -> Bitboard {
+        let snipers = (attacks::rook_attacks(king, Bitboard(0)) & self.rooks_and_queens()) |
+                      (attacks::bishop_attacks(king, Bitboard(0)) & self.bishops_and_queens());
+
+        let mut blockers = Bitboard(0);
+
+        for sniper in snipers & sliders {
+            let b = attacks::between(king, sniper) & self.occupied;
+
+            if !b.more_than_one() {
+                blockers.add_all(b);
+            }
+        }
+
+        blockers
+    }
+
+    /// Pieces of the given side that are pinned to their own king.
+    pub fn pinned(&self, color: Color) -> Bitboard {
+        self.king_of(color).map_or(Bitboard(0), |king| {
+            self.slider_blockers(self.by_color(!color), king) & self.by_color(color)
+        })
+    }
+
+    /// The enemy slider that pins the piece on `sq` to its king, if any.
+    pub fn pinner(&self, sq: Square) -> Option<Square> {
+        let color = self.color_at(sq)?;
+        let king = self.king_of(color)?;
+
+        let snipers = (attacks::rook_attacks(king, Bitboard(0)) & self.rooks_and_queens()) |
+                      (attacks::bishop_attacks(king, Bitboard(0)) & self.bishops_and_queens());
+
+        (snipers & self.by_color(!color)).find(|&sniper| {
+            attacks::between(king, sniper) & self.occupied == Bitboard::from_square(sq)
+        })
+    }
+
+    /// Pieces of the given side that uncover an attack on the enemy king
+    /// when moving off the line.
+    pub fn discovered_check_candidates(&self, color: Color) -> Bitboard {
+        self.king_of(!color).map_or(Bitboard(0), |king| {
+            self.slider_blockers(self.by_color(color), king) & self.by_color(color)
+        })
+    }
+
+    /// Squares attacked by the slider on `sq` through exactly one blocker
+    /// of either color. Empty for other pieces.
+    pub fn xray_attacks_from(&self, sq: Square) -> Bitboard {
+        match self.piece_at(sq) {
+            Some(piece) if self.sliders().contains(sq) => {
+                let direct = attacks::attacks(sq, piece, self.occupied);
+                let blockers = direct & self.occupied;
+                attacks::attacks(sq, piece, self.occupied ^ blockers) & !direct
+            },
+            _ => Bitboard(0),
+        }
+    }
+
+    /// Number of squares attacked by the piece on `sq` that are not occupied
+    /// by pieces of the same color.
+    pub fn mobility(&self, sq: Square) -> usize {
+        self.color_at(sq).map_or(0, |color| {
+            (self.attacks_from(sq) & !self.by_color(color)).count()
+        })
+    }
+
+    /// Squares attacked by any piece of the given side.
+    pub fn attacked_by(&self, color: Color) -> Bitboard {
+        self.by_color(color).fold(Bitboard(0), |attacked, sq| attacked | self.attacks_from(sq))
+    }
+
+    /// Computes the attacks of all pieces at once.
+    pub fn attack_map(&self) -> AttackMap {
+        let mut map = AttackMap {
+            attacks: [Bitboard(0); 64],
+            attackers: [Bitboard(0); 64],
+            white: self.white(),
+            black: self.black(),
+        };
+
+        for from in self.occupied {
+            let attacks = self.attacks_from(from);
+            map.attacks[from.index() as usize] = attacks;
+            for to in attacks {
+                map.attackers[to.index() as usize].add(from);
+            }
+        }
+
+        map
+    }
+
     pub fn pieces(&self) -> Pieces {
         Pieces {
             pawns: self.pawns(),
@@ -266,6 +359,77 @@ impl fmt::Debug for Board {
     }
 }
 
+/// Attacks of all pieces on a `Board`, indexed by origin and by target
+/// square.
+///
+/// # Examples
+///
+/// ```
+/// use shakmaty::{Board, Color};
+/// use shakmaty::square;
+///
+/// let map = Board::new().attack_map();
+///
+/// assert_eq!(map.count(square::F3, Color::White), 3);
+/// assert_eq!(map.count(square::F3, Color::Black), 0);
+/// assert_eq!(map.mobility(square::G1), 2);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AttackMap {
+    attacks: [Bitboard; 64],
+    attackers: [Bitboard; 64],
+    white: Bitboard,
+    black: Bitboard,
+}
+
+impl AttackMap {
+    /// Squares attacked by the piece on `sq`.
+    pub fn attacks_from(&self, sq: Square) -> Bitboard {
+        self.attacks[sq.index() as usize]
+    }
+
+    /// Pieces of both colors attacking `sq`.
+    pub fn attackers(&self, sq: Square) -> Bitboard {
+        self.attackers[sq.index() as usize]
+    }
+
+    /// Pieces of the given side attacking `sq`.
+    pub fn attackers_of(&self, sq: Square, color: Color) -> Bitboard {
+        self.attackers(sq) & self.by_color(color)
+    }
+
+    /// Number of pieces of the given side attacking `sq`.
+    pub fn count(&self, sq: Square, color: Color) -> usize {
+        self.attackers_of(sq, color).count()
+    }
+
+    /// Squares attacked by any piece of the given side.
+    pub fn attacked_by(&self, color: Color) -> Bitboard {
+        self.by_color(color).fold(Bitboard(0), |attacked, sq| attacked | self.attacks_from(sq))
+    }
+
+    /// Number of squares attacked by the piece on `sq` that are not occupied
+    /// by pieces of the same color.
+    pub fn mobility(&self, sq: Square) -> usize {
+        let own = if self.white.contains(sq) { self.white } else { self.black };
+        (self.attacks_from(sq) & !own).count()
+    }
+
+    fn by_color(&self, color: Color) -> Bitboard {
+        color.fold(self.white, self.black)
+    }
+}
+
+impl fmt::Debug for AttackMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+         .entries((0..64).map(Square::new)
+                         .filter(|&sq| self.attackers(sq).any())
+                         .map(|sq| (sq, self.attackers(sq))))
+         .finish()
+    }
+}
+
 /// Iterator over the pieces of a `Board`.
 #[derive(Clone)]
 pub struct Pieces {
@@ -358,4 +522,47 @@ mod tests {
         assert_eq!(board.piece_at(square::C1), Some(Black.queen()));
         assert!(board.promoted().contains(square::C1));
     }
+
+    #[test]
+    fn test_pins() {
+        let board: Board = "4k3/4r3/8/1b6/8/3N4/4B3/R3K2Q".parse().expect("valid fen");
+
+        assert_eq!(board.pinned(White), Bitboard::from_square(square::E2));
+        assert_eq!(board.pinner(square::E2), Some(square::E7));
+        assert_eq!(board.pinner(square::D3), None);
+        assert_eq!(board.pinned(Black), Bitboard(0));
+        assert_eq!(board.discovered_check_candidates(White), Bitboard(0));
+
+        let board: Board = "4k3/8/8/1b6/4N3/8/4B3/R3K2Q".parse().expect("valid fen");
+        assert_eq!(board.pinned(White), Bitboard(0));
+        assert_eq!(board.discovered_check_candidates(White), Bitboard(0));
+
+        let board: Board = "4k3/8/8/8/4N3/8/8/4R1K1".parse().expect("valid fen");
+        assert_eq!(board.discovered_check_candidates(White), Bitboard::from_square(square::E4));
+        assert_eq!(board.pinner(square::E4), None);
+    }
+
+    #[test]
+    fn test_attack_map() {
+        let board: Board = "4k3/8/8/3p4/8/8/3Q4/3RK3".parse().expect("valid fen");
+        let map = board.attack_map();
+
+        for sq in (0..64).map(Square::new) {
+            assert_eq!(map.attacks_from(sq), board.attacks_from(sq));
+            for &color in &[White, Black] {
+                assert_eq!(map.attackers_of(sq, color), board.attacks_to(sq, color, board.occupied()));
+            }
+            assert_eq!(map.mobility(sq), board.mobility(sq));
+        }
+
+        assert_eq!(map.count(square::D5, White), 1);
+        assert_eq!(map.attacked_by(Black), board.attacked_by(Black));
+
+        let rook = board.xray_attacks_from(square::D1);
+        assert!(rook.contains(square::D3) && rook.contains(square::D5) && rook.contains(square::H1));
+        assert!(!rook.contains(square::D6) && !rook.contains(square::D2));
+        assert_eq!(board.xray_attacks_from(square::D2),
+                   [square::D6, square::D7, square::D8].iter().cloned().collect());
+        assert_eq!(board.xray_attacks_from(square::E1), Bitboard(0));
+    }
 }
@@ -25,6 +25,7 @@ const ROOK_DELTAS: [i8; 4] = [8, 1, -8, -1];
 const BISHOP_DELTAS: [i8; 4] = [9, 7, -9, -7];
 const KING_DELTAS: [i8; 8] = [9, 8, 7, 1, -9, -8, -7, -1];
 const KNIGHT_DELTAS: [i8; 8] = [17, 15, 10, 6, -17, -15, -10, -6];
+const ALFIL_DELTAS: [i8; 4] = [18, 14, -18, -14];
 const WHITE_PAWN_DELTAS: [i8; 2] = [7, 9];
 const BLACK_PAWN_DELTAS: [i8; 2] = [-7, -9];
 
@@ -93,6 +94,8 @@ fn generate() -> io::Result<()> {
 
     let mut knight_attacks = [Bitboard(0); 64];
     let mut king_attacks = [Bitboard(0); 64];
+    let mut ferz_attacks = [Bitboard(0); 64];
+    let mut alfil_attacks = [Bitboard(0); 64];
     let mut white_pawn_attacks = [Bitboard(0); 64];
     let mut black_pawn_attacks = [Bitboard(0); 64];
 
@@ -105,6 +108,8 @@ fn generate() -> io::Result<()> {
         let sq = Square::from_index(s as i8).expect("square index s in range");
         knight_attacks[s] = step_attacks(sq, &KNIGHT_DELTAS);
         king_attacks[s] = step_attacks(sq, &KING_DELTAS);
+        ferz_attacks[s] = step_attacks(sq, &BISHOP_DELTAS);
+        alfil_attacks[s] = step_attacks(sq, &ALFIL_DELTAS);
         white_pawn_attacks[s] = step_attacks(sq, &WHITE_PAWN_DELTAS);
         black_pawn_attacks[s] = step_attacks(sq, &BLACK_PAWN_DELTAS);
         init_magics(sq, &magics::ROOK_MAGICS[s], 12, &mut attacks, &ROOK_DELTAS);
@@ -138,6 +143,8 @@ fn generate() -> io::Result<()> {
 
     dump_slice(&mut f, "KNIGHT_ATTACKS", "u64", &knight_attacks)?;
     dump_slice(&mut f, "KING_ATTACKS", "u64", &king_attacks)?;
+    dump_slice(&mut f, "FERZ_ATTACKS", "u64", &ferz_attacks)?;
+    dump_slice(&mut f, "ALFIL_ATTACKS", "u64", &alfil_attacks)?;
     dump_slice(&mut f, "WHITE_PAWN_ATTACKS", "u64", &white_pawn_attacks)?;
     dump_slice(&mut f, "BLACK_PAWN_ATTACKS", "u64", &black_pawn_attacks)?;
 
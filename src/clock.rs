@@ -0,0 +1,417 @@
+// This file is part of the shakmaty library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Time controls and game clocks.
+//!
+//! # Examples
+//!
+//! Parsing a PGN `TimeControl` tag:
+//!
+//! ```
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! use std::time::Duration;
+//! use shakmaty::Color::White;
+//! use shakmaty::clock::{Bonus, Clock, TimeControl};
+//!
+//! let tc: TimeControl = "300+2".parse()?;
+//! let mut clock = Clock::new(tc, Bonus::Increment);
+//!
+//! clock.press(Duration::from_secs(10));
+//! assert_eq!(clock.remaining(White), Duration::from_secs(292));
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use std::fmt;
+use std::cmp::min;
+use std::str::FromStr;
+use std::error::Error;
+use std::time::Duration;
+
+use types::{Color, White};
+use position::{Position, Outcome};
+
+/// Error when parsing an invalid time control.
+pub struct InvalidTimeControl {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidTimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidTimeControl").finish()
+    }
+}
+
+impl fmt::Display for InvalidTimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid time control".fmt(f)
+    }
+}
+
+impl Error for InvalidTimeControl {
+    fn description(&self) -> &str {
+        "invalid time control"
+    }
+}
+
+impl From<()> for InvalidTimeControl {
+    fn from(_: ()) -> InvalidTimeControl {
+        InvalidTimeControl { _priv: () }
+    }
+}
+
+/// A period of a time control.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Period {
+    /// Number of moves to be made in the period, or `None` for the rest of
+    /// the game.
+    pub moves: Option<u32>,
+    /// Time added to the clock at the start of the period.
+    pub time: Duration,
+    /// Time added, or delay granted, for each move of the period.
+    pub bonus: Duration,
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(moves) = self.moves {
+            write!(f, "{}/", moves)?;
+        }
+
+        write!(f, "{}", self.time.as_secs())?;
+
+        if self.bonus != Duration::from_secs(0) {
+            write!(f, "+{}", self.bonus.as_secs())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A time control as a sequence of periods. The last period repeats.
+///
+/// An empty time control (`-` in PGN) means the game is untimed.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TimeControl {
+    pub periods: Vec<Period>,
+}
+
+impl TimeControl {
+    /// The time control of an untimed game.
+    pub fn untimed() -> TimeControl {
+        TimeControl { periods: Vec::new() }
+    }
+
+    pub fn is_untimed(&self) -> bool {
+        self.periods.is_empty()
+    }
+
+    /// Parses a time control in the format of the PGN `TimeControl` tag,
+    /// for example `300+2`, `40/7200:3600` or `-`.
+    pub fn from_bytes(s: &[u8]) -> Result<TimeControl, InvalidTimeControl> {
+        if s == b"-" {
+            return Ok(TimeControl::untimed());
+        }
+
+        let periods = s.split(|ch| *ch == b':')
+                       .map(parse_period)
+                       .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TimeControl { periods })
+    }
+
+    fn period(&self, index: usize) -> Option<&Period> {
+        self.periods.get(index).or_else(|| self.periods.last())
+    }
+}
+
+fn parse_period(s: &[u8]) -> Result<Period, InvalidTimeControl> {
+    let (moves, rest) = match s.iter().position(|ch| *ch == b'/') {
+        Some(0) | None => (None, s),
+        Some(slash) => (Some(parse_number(&s[..slash])?), &s[slash + 1..]),
+    };
+
+    if moves == Some(0) {
+        return Err(InvalidTimeControl { _priv: () });
+    }
+
+    let (time, bonus) = match rest.iter().position(|ch| *ch == b'+') {
+        Some(plus) => (parse_number(&rest[..plus])?, parse_number(&rest[plus + 1..])?),
+        None => (parse_number(rest)?, 0),
+    };
+
+    Ok(Period {
+        moves,
+        time: Duration::from_secs(u64::from(time)),
+        bonus: Duration::from_secs(u64::from(bonus)),
+    })
+}
+
+fn parse_number(s: &[u8]) -> Result<u32, InvalidTimeControl> {
+    if s.is_empty() || s.len() > 9 || !s.iter().all(|ch| b'0' <= *ch && *ch <= b'9') {
+        return Err(InvalidTimeControl { _priv: () });
+    }
+
+    Ok(s.iter().fold(0, |n, ch| n * 10 + u32::from(ch - b'0')))
+}
+
+impl FromStr for TimeControl {
+    type Err = InvalidTimeControl;
+
+    fn from_str(s: &str) -> Result<TimeControl, InvalidTimeControl> {
+        TimeControl::from_bytes(s.as_bytes())
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_untimed() {
+            return write!(f, "-");
+        }
+
+        for (i, period) in self.periods.iter().enumerate() {
+            if i > 0 {
+                write!(f, ":")?;
+            }
+            write!(f, "{}", period)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How the per-move bonus of a period is applied.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Bonus {
+    /// Fischer increment: the bonus is added after each move.
+    Increment,
+    /// Bronstein delay: the time used for a move is given back after the
+    /// move, up to the bonus.
+    Bronstein,
+    /// Simple delay: the clock only starts running after the bonus has
+    /// elapsed.
+    Delay,
+}
+
+#[derive(Clone, Debug)]
+struct Side {
+    remaining: Duration,
+    period: usize,
+    moves: u32,
+}
+
+/// A chess clock for both players.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    bonus: Bonus,
+    sides: [Side; 2],
+    turn: Color,
+    flag: Option<Color>,
+}
+
+impl Clock {
+    /// Sets up a clock for a new game, with White to move.
+    pub fn new(control: TimeControl, bonus: Bonus) -> Clock {
+        let remaining = control.period(0).map_or(Duration::from_secs(0), |p| p.time);
+        let side = Side { remaining, period: 0, moves: 0 };
+
+        Clock {
+            control,
+            bonus,
+            sides: [side.clone(), side],
+            turn: White,
+            flag: None,
+        }
+    }
+
+    pub fn time_control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// The side whose clock is running.
+    pub fn turn(&self) -> Color {
+        self.turn
+    }
+
+    /// The time left on the clock of `color`, not counting the time spent
+    /// on a move in progress.
+    pub fn remaining(&self, color: Color) -> Duration {
+        self.sides[color as usize].remaining
+    }
+
+    /// The side whose flag has fallen, if any.
+    pub fn flag(&self) -> Option<Color> {
+        self.flag
+    }
+
+    fn allowance(&self) -> Duration {
+        let side = &self.sides[self.turn as usize];
+        match self.bonus {
+            Bonus::Delay => side.remaining + self.control.period(side.period).map_or(Duration::from_secs(0), |p| p.bonus),
+            Bonus::Increment | Bonus::Bronstein => side.remaining,
+        }
+    }
+
+    /// Checks whether the side to move runs out of time after spending
+    /// `elapsed` on its current move. Records and returns the flag fall.
+    pub fn check_flag(&mut self, elapsed: Duration) -> Option<Color> {
+        if self.flag.is_none() && !self.control.is_untimed() && elapsed > self.allowance() {
+            self.flag = Some(self.turn);
+        }
+
+        self.flag
+    }
+
+    /// Ends the move of the side to move, which took `elapsed`, and starts
+    /// the clock of the opponent.
+    ///
+    /// Returns the side whose flag has fallen, if any. Once a flag has
+    /// fallen, the clock no longer changes.
+    pub fn press(&mut self, elapsed: Duration) -> Option<Color> {
+        if self.check_flag(elapsed).is_some() || self.control.is_untimed() {
+            return self.flag;
+        }
+
+        let allowance = self.allowance();
+        let bonus = self.bonus;
+        let control = &self.control;
+        let side = &mut self.sides[self.turn as usize];
+        let period = control.period(side.period).expect("timed control has periods").clone();
+
+        side.remaining = match bonus {
+            Bonus::Increment => allowance - elapsed + period.bonus,
+            Bonus::Bronstein => allowance - elapsed + min(elapsed, period.bonus),
+            Bonus::Delay => min(allowance - elapsed, side.remaining),
+        };
+
+        side.moves += 1;
+        if period.moves == Some(side.moves) {
+            side.period += 1;
+            side.moves = 0;
+            side.remaining += control.period(side.period).map_or(Duration::from_secs(0), |p| p.time);
+        }
+
+        self.turn = !self.turn;
+        None
+    }
+
+    /// The outcome of the game by flag fall, if any.
+    ///
+    /// The side that ran out of time loses, unless the opponent could not
+    /// possibly checkmate in the position, in which case the game is drawn.
+    pub fn outcome<P: Position>(&self, pos: &P) -> Option<Outcome> {
+        self.flag.map(|loser| {
+            if pos.has_insufficient_material(!loser) {
+                Outcome::Draw
+            } else {
+                Outcome::Decisive { winner: !loser }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fen::Fen;
+    use position::Chess;
+    use types::Black;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn test_read_write() {
+        for tc in &["-", "300", "300+2", "40/7200:3600", "40/5400+30:1800+30", "40/9000"] {
+            let result = tc.parse::<TimeControl>().expect("valid time control").to_string();
+            assert_eq!(*tc, result);
+        }
+
+        for tc in &["", "?", "*180", "300+", "/300", "0/300", "40/", "300:", "1e3"] {
+            assert!(tc.parse::<TimeControl>().is_err(), "{}", tc);
+        }
+    }
+
+    #[test]
+    fn test_periods() {
+        let mut clock = Clock::new("2/100:60+5".parse().expect("valid time control"), Bonus::Increment);
+
+        for _ in 0..4 {
+            assert_eq!(clock.press(secs(10)), None);
+        }
+
+        assert_eq!(clock.remaining(White), secs(80 + 60));
+        assert_eq!(clock.remaining(Black), secs(80 + 60));
+
+        clock.press(secs(10));
+        assert_eq!(clock.remaining(White), secs(135));
+        assert_eq!(clock.turn(), Black);
+
+        assert_eq!(clock.press(secs(141)), Some(Black));
+        assert_eq!(clock.remaining(Black), secs(140));
+        assert_eq!(clock.press(secs(1)), Some(Black));
+    }
+
+    #[test]
+    fn test_delays() {
+        let mut bronstein = Clock::new("60+5".parse().expect("valid time control"), Bonus::Bronstein);
+        bronstein.press(secs(3));
+        bronstein.press(secs(10));
+        assert_eq!(bronstein.remaining(White), secs(60));
+        assert_eq!(bronstein.remaining(Black), secs(55));
+
+        let mut delay = Clock::new("60+5".parse().expect("valid time control"), Bonus::Delay);
+        delay.press(secs(3));
+        delay.press(secs(10));
+        assert_eq!(delay.remaining(White), secs(60));
+        assert_eq!(delay.remaining(Black), secs(55));
+        assert_eq!(delay.check_flag(secs(65)), None);
+        assert_eq!(delay.check_flag(secs(66)), Some(White));
+    }
+
+    #[test]
+    fn test_flag_outcome() {
+        let mut clock = Clock::new("60".parse().expect("valid time control"), Bonus::Increment);
+        assert_eq!(clock.check_flag(secs(61)), Some(White));
+
+        let pos: Chess = "8/8/4k3/8/8/3KB3/8/8 w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+        assert_eq!(clock.outcome(&pos), Some(Outcome::Draw));
+
+        let pos: Chess = "8/8/4k3/8/8/3K4/8/r7 w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+        assert_eq!(clock.outcome(&pos), Some(Outcome::Decisive { winner: Black }));
+
+        let pos: Chess = "8/8/4k3/8/8/3K4/8/R7 w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+        assert_eq!(clock.outcome(&pos), Some(Outcome::Draw));
+    }
+}
@@ -0,0 +1,230 @@
+// This file is part of the shakmaty library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Legal destinations by origin square, as used by board user interfaces.
+//!
+//! # Examples
+//!
+//! ```
+//! use shakmaty::Chess;
+//! use shakmaty::square;
+//! use shakmaty::dests::Dests;
+//!
+//! let pos = Chess::default();
+//! let dests = Dests::new(&pos);
+//!
+//! assert_eq!(dests.get(square::G1).count(), 2);
+//! assert_eq!(dests.to_string(), "bqs gvx iqy jrz ksA ltB muC nvD owE pxF");
+//! ```
+
+use std::fmt;
+
+use square::Square;
+use bitboard::Bitboard;
+use types::{Move, Role};
+use setup::CastlingSide;
+use position::Position;
+use movelist::MoveList;
+
+const PIOTR: &[u8; 64] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!?";
+
+/// Encodes a square as a single character, as in the compact move format
+/// of lichess.org.
+///
+/// # Examples
+///
+/// ```
+/// use shakmaty::square;
+/// use shakmaty::dests::{piotr, from_piotr};
+///
+/// assert_eq!(piotr(square::E2), 'm');
+/// assert_eq!(from_piotr('m'), Some(square::E2));
+/// ```
+pub fn piotr(sq: Square) -> char {
+    char::from(PIOTR[sq.index() as usize])
+}
+
+/// Decodes a square encoded with `piotr()`.
+pub fn from_piotr(ch: char) -> Option<Square> {
+    PIOTR.iter()
+         .position(|&c| char::from(c) == ch)
+         .map(|index| Square::new(index as i8))
+}
+
+/// Legal destinations of each piece on the board.
+///
+/// Castling moves are included as king moves to the rook. If king and rook
+/// are on their standard starting squares, castling is also included as a
+/// king move two squares towards the rook.
+///
+/// Drops and null moves have no origin square and are not included.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Dests {
+    dests: [Bitboard; 64],
+    promotions: Bitboard,
+}
+
+impl Dests {
+    /// Collects the legal destinations in `pos`.
+    pub fn new<P: Position>(pos: &P) -> Dests {
+        let mut moves = MoveList::new();
+        pos.legal_moves(&mut moves);
+
+        let mut dests = Dests {
+            dests: [Bitboard(0); 64],
+            promotions: Bitboard(0),
+        };
+
+        for m in &moves {
+            match *m {
+                Move::Normal { role, from, to, promotion, .. } => {
+                    dests.dests[from.index() as usize].add(to);
+                    if promotion.is_some() || (role == Role::Pawn && to.rank() % 7 == 0) {
+                        dests.promotions.add(from);
+                    }
+                },
+                Move::EnPassant { from, to } => dests.dests[from.index() as usize].add(to),
+                Move::Castle { king, rook } => {
+                    let side = if king < rook { CastlingSide::KingSide } else { CastlingSide::QueenSide };
+                    dests.dests[king.index() as usize].add(rook);
+                    if king.file() == 4 && (rook.file() == 0 || rook.file() == 7) {
+                        dests.dests[king.index() as usize].add(side.king_to(pos.turn()));
+                    }
+                },
+                Move::Put { .. } | Move::Exchange { .. } | Move::Null => (),
+            }
+        }
+
+        dests
+    }
+
+    /// Gets the legal destinations of the piece on `from`.
+    pub fn get(&self, from: Square) -> Bitboard {
+        self.dests[from.index() as usize]
+    }
+
+    /// Gets the squares of pieces that have at least one legal destination.
+    pub fn origins(&self) -> Bitboard {
+        self.dests.iter().zip(0..).fold(Bitboard(0), |origins, (dests, index)| {
+            if dests.any() { origins.with(Square::new(index)) } else { origins }
+        })
+    }
+
+    /// Tests if moves of the piece on `from` require a promotion choice.
+    pub fn is_promotion(&self, from: Square) -> bool {
+        self.promotions.contains(from)
+    }
+
+    /// Gets the squares of pawns that promote when moved.
+    pub fn promotions(&self) -> Bitboard {
+        self.promotions
+    }
+
+    /// Tests if there are no legal destinations at all.
+    pub fn is_empty(&self) -> bool {
+        self.origins().is_empty()
+    }
+}
+
+impl fmt::Debug for Dests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+         .entries(self.origins().map(|from| (from, self.get(from))))
+         .finish()
+    }
+}
+
+/// Serializes the destinations in the compact format of lichess.org:
+/// origins with their destinations, each square encoded with `piotr()` and
+/// groups separated by spaces.
+impl fmt::Display for Dests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for from in self.origins() {
+            if !first {
+                f.write_str(" ")?;
+            }
+            first = false;
+
+            write!(f, "{}", piotr(from))?;
+            for to in self.get(from) {
+                write!(f, "{}", piotr(to))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use square;
+    use fen::Fen;
+    use position::Chess;
+
+    #[test]
+    fn test_piotr() {
+        for sq in (0..64).map(Square::new) {
+            assert_eq!(from_piotr(piotr(sq)), Some(sq));
+        }
+        assert_eq!(piotr(square::A1), 'a');
+        assert_eq!(piotr(square::H8), '?');
+        assert_eq!(from_piotr('#'), None);
+    }
+
+    #[test]
+    fn test_castling_and_promotion() {
+        let pos: Chess = "r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+
+        let dests = Dests::new(&pos);
+
+        let king = dests.get(square::E1);
+        assert!(king.contains(square::H1));
+        assert!(king.contains(square::G1));
+        assert!(king.contains(square::A1));
+        assert!(king.contains(square::C1));
+
+        assert!(dests.is_promotion(square::B7));
+        assert!(!dests.is_promotion(square::E1));
+        assert_eq!(dests.promotions(), Bitboard::from_square(square::B7));
+
+        let mut moves = MoveList::new();
+        pos.legal_moves_from(square::B7, &mut moves);
+        assert_eq!(moves.len(), 8);
+        assert_eq!(dests.get(square::B7).count(), 2);
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        let pos: Chess = "4k3/8/8/8/8/8/8/1R2K1R1 w GB - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+
+        let dests = Dests::new(&pos);
+
+        // The rooks are not on their standard starting squares, so castling
+        // is only included as a king move to the rook.
+        let king = dests.get(square::E1);
+        assert!(king.contains(square::B1));
+        assert!(king.contains(square::G1));
+        assert!(!king.contains(square::C1));
+        assert_eq!(king.count(), 7);
+    }
+}
@@ -203,10 +203,11 @@ impl fmt::Display for Board {
 }
 
 /// A parsed FEN.
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub struct Fen {
     pub board: Board,
     pub pockets: Option<Pockets>,
+    pub prisons: Option<Pockets>,
     pub turn: Color,
     pub castling_rights: Bitboard,
     pub ep_square: Option<Square>,
@@ -218,6 +219,7 @@ pub struct Fen {
 impl Setup for Fen {
     fn board(&self) -> &Board { &self.board }
     fn pockets(&self) -> Option<&Pockets> { self.pockets.as_ref() }
+    fn prisons(&self) -> Option<&Pockets> { self.prisons.as_ref() }
     fn turn(&self) -> Color { self.turn }
     fn castling_rights(&self) -> Bitboard { self.castling_rights }
     fn ep_square(&self) -> Option<Square> { self.ep_square }
@@ -231,6 +233,7 @@ impl Default for Fen {
         Fen {
             board: Board::default(),
             pockets: None,
+            prisons: None,
             turn: White,
             castling_rights: bitboard::CORNERS,
             ep_square: None,
@@ -269,20 +272,36 @@ impl FromStr for Fen {
 
         let board_part = parts.next().expect("splits have at least one part");
 
-        let (board_part, pockets) = if board_part.ends_with(']') {
+        let (board_part, pockets, prisons) = if board_part.ends_with(']') {
             let split_point = board_part.find('[').ok_or(FenError::InvalidBoard)?;
+            let mut pocket_parts = board_part[(split_point + 1)..(board_part.len() - 1)].splitn(2, '|');
+
             let mut pockets = Pockets::default();
-            for ch in board_part[(split_point + 1)..(board_part.len() - 1)].chars() {
+            for ch in pocket_parts.next().expect("splits have at least one part").chars() {
                 let piece = Piece::from_char(ch).ok_or(FenError::InvalidPocket)?;
                 pockets.add(piece);
             }
-            (&board_part[..split_point], Some(pockets))
+
+            let prisons = match pocket_parts.next() {
+                Some(prison_part) => {
+                    let mut prisons = Pockets::default();
+                    for ch in prison_part.chars() {
+                        let piece = Piece::from_char(ch).ok_or(FenError::InvalidPocket)?;
+                        prisons.add(piece.role.of(!piece.color));
+                    }
+                    Some(prisons)
+                },
+                None => None,
+            };
+
+            (&board_part[..split_point], Some(pockets), prisons)
         } else {
-            (board_part, None)
+            (board_part, None, None)
         };
 
         result.board = board_part.parse()?;
         result.pockets = pockets;
+        result.prisons = prisons;
 
         result.turn = match parts.next() {
             Some("w") | None => White,
@@ -418,10 +437,24 @@ pub fn board_fen(board: &Board, opts: &FenOpts) -> String {
     fen
 }
 
+fn prison_fen(prisons: &Pockets) -> String {
+    // Prisons hold pieces of the opponent, so the captured pieces are
+    // written in their own color.
+    Pockets {
+        white: prisons.black.clone(),
+        black: prisons.white.clone(),
+    }.to_string()
+}
+
 /// Create an EPD such as `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -`.
 pub fn epd(setup: &Setup, opts: &FenOpts) -> String {
-    let pockets = setup.pockets()
-                       .map_or("".to_owned(), |p| format!("[{}]", p));
+    let pockets = match (setup.pockets(), setup.prisons()) {
+        (pockets, Some(prisons)) => format!("[{}|{}]",
+                                            pockets.map_or("".to_owned(), |p| p.to_string()),
+                                            prison_fen(prisons)),
+        (Some(pockets), None) => format!("[{}]", pockets),
+        (None, None) => "".to_owned(),
+    };
 
     let checks = setup.remaining_checks()
                       .map_or("".to_owned(), |r| format!(" {}", r));
@@ -0,0 +1,432 @@
+// This file is part of the shakmaty library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! A game with move history, takebacks and repetition detection.
+
+use types::{Color, Move, Pockets};
+use setup::Setup;
+use position::{Position, Outcome, Termination, IllegalMove};
+use zobrist;
+
+/// Identifies positions for repetition detection. Pockets are not part of
+/// the Zobrist hash, so they are compared separately.
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct RepetitionKey {
+    zobrist: u64,
+    pockets: Option<Pockets>,
+    prisons: Option<Pockets>,
+}
+
+impl RepetitionKey {
+    fn new<S: Setup>(setup: &S) -> RepetitionKey {
+        RepetitionKey {
+            zobrist: zobrist::hash(setup),
+            pockets: setup.pockets().cloned(),
+            prisons: setup.prisons().cloned(),
+        }
+    }
+}
+
+/// A sequence of moves played from a starting position.
+///
+/// Keeps every position reached, so that moves can be taken back and
+/// repetitions can be detected. `outcome()` extends the outcome of the
+/// current position with automatic draws by fivefold repetition and the
+/// 75-move rule, with draws claimed by a player and with forfeits.
+///
+/// # Examples
+///
+/// ```
+/// # use std::error::Error;
+/// #
+/// # fn try_main() -> Result<(), Box<Error>> {
+/// # use shakmaty::uci::Uci;
+/// use shakmaty::{Chess, Game};
+///
+/// let mut game: Game<Chess> = Game::default();
+///
+/// for _ in 0..2 {
+///     for uci in &["g1f3", "g8f6", "f3g1", "f6g8"] {
+///         let m = uci.parse::<Uci>()?.to_move(game.position())?;
+///         game.play(&m)?;
+///     }
+/// }
+///
+/// assert_eq!(game.repetitions(), 3);
+/// assert_eq!(game.undo().map(|m| m.to_string()), Some("Nf6-g8".to_owned()));
+/// assert_eq!(game.repetitions(), 2);
+/// #
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #     try_main().unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Game<P> {
+    positions: Vec<P>,
+    moves: Vec<Move>,
+    keys: Vec<RepetitionKey>,
+    ended: Option<(Termination, Outcome)>,
+}
+
+impl<P: Position + Clone> Game<P> {
+    /// Starts a new game from the given position.
+    pub fn new(pos: P) -> Game<P> {
+        Game {
+            keys: vec![RepetitionKey::new(&pos)],
+            positions: vec![pos],
+            moves: Vec::new(),
+            ended: None,
+        }
+    }
+
+    /// The starting position.
+    pub fn initial_position(&self) -> &P {
+        &self.positions[0]
+    }
+
+    /// The current position.
+    pub fn position(&self) -> &P {
+        self.positions.last().expect("at least the starting position")
+    }
+
+    /// The moves played so far.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Plays a move.
+    ///
+    /// # Errors
+    ///
+    /// Returns `IllegalMove` if the move is not legal in the current
+    /// position. The game is not changed in this case.
+    pub fn play(&mut self, m: &Move) -> Result<(), IllegalMove> {
+        self.position().validate(m)?;
+        self.play_unchecked(m);
+        Ok(())
+    }
+
+    /// Plays a move. It is the callers responsibility to ensure the move is
+    /// legal.
+    pub fn play_unchecked(&mut self, m: &Move) {
+        let mut pos = self.position().clone();
+        pos.play_unchecked(m);
+        self.keys.push(RepetitionKey::new(&pos));
+        self.positions.push(pos);
+        self.moves.push(m.clone());
+    }
+
+    /// Takes back the last move, if any.
+    pub fn undo(&mut self) -> Option<Move> {
+        self.moves.pop().map(|m| {
+            self.ended = None;
+            self.positions.pop();
+            self.keys.pop();
+            m
+        })
+    }
+
+    /// Number of times the current position has occured in the game,
+    /// including the current occurence.
+    ///
+    /// Only positions since the last pawn move or capture are considered,
+    /// as earlier positions can not be repeated.
+    pub fn repetitions(&self) -> usize {
+        let key = self.keys.last().expect("at least the starting position");
+        let reversible = self.position().halfmove_clock() as usize;
+        self.keys.iter().rev().take(reversible + 1).filter(|k| *k == key).count()
+    }
+
+    /// Tests if the current position occured at least three times.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 3
+    }
+
+    /// Tests if the current position occured at least five times.
+    pub fn is_fivefold_repetition(&self) -> bool {
+        self.repetitions() >= 5
+    }
+
+    /// Tests if 50 moves have been played by each side without a pawn move
+    /// or capture.
+    pub fn is_fifty_moves(&self) -> bool {
+        self.position().halfmove_clock() >= 100
+    }
+
+    /// Tests if 75 moves have been played by each side without a pawn move
+    /// or capture.
+    pub fn is_seventyfive_moves(&self) -> bool {
+        self.position().halfmove_clock() >= 150
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.outcome().is_some()
+    }
+
+    /// The outcome of the game, including automatic and claimed draws and
+    /// forfeits.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.position().outcome().or_else(|| {
+            if self.is_fivefold_repetition() || self.is_seventyfive_moves() {
+                Some(Outcome::Draw)
+            } else {
+                self.ended.as_ref().map(|&(_, ref outcome)| outcome.clone())
+            }
+        })
+    }
+
+    /// The reason the game is over, including automatic and claimed draws
+    /// and forfeits.
+    pub fn termination(&self) -> Option<Termination> {
+        self.position().termination().or_else(|| {
+            if self.is_fivefold_repetition() {
+                Some(Termination::FivefoldRepetition)
+            } else if self.is_seventyfive_moves() {
+                Some(Termination::SeventyFiveMoves)
+            } else {
+                self.ended.as_ref().map(|&(termination, _)| termination)
+            }
+        })
+    }
+
+    /// A draw the player to move may claim, if any.
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if self.is_threefold_repetition() {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.is_fifty_moves() {
+            Some(Termination::FiftyMoves)
+        } else {
+            None
+        }
+    }
+
+    /// A draw the player to move may claim by announcing `m`, because the
+    /// position after `m` occurs for the third time or completes 50 moves
+    /// without pawn move or capture. Returns `None` if `m` is illegal.
+    pub fn claimable_draw_with(&self, m: &Move) -> Option<Termination> {
+        if !self.position().is_legal(m) {
+            return None;
+        }
+
+        let mut pos = self.position().clone();
+        pos.play_unchecked(m);
+
+        let key = RepetitionKey::new(&pos);
+        let reversible = pos.halfmove_clock() as usize;
+        let repetitions = 1 + self.keys.iter().rev().take(reversible).filter(|k| **k == key).count();
+
+        if repetitions >= 3 {
+            Some(Termination::ThreefoldRepetition)
+        } else if pos.halfmove_clock() >= 100 {
+            Some(Termination::FiftyMoves)
+        } else {
+            None
+        }
+    }
+
+    /// Claims a draw, ending the game. Returns `None` if the game is
+    /// already over or there is no draw to claim.
+    pub fn claim_draw(&mut self) -> Option<Termination> {
+        if self.is_game_over() {
+            return None;
+        }
+
+        let claimed = self.claimable_draw();
+        self.ended = claimed.map(|termination| (termination, Outcome::Draw));
+        claimed
+    }
+
+    /// Claims a draw by announcing `m`. If the claim is correct, `m` is
+    /// played and the game ends in a draw (unless `m` ends the game by
+    /// itself, for example with checkmate). Otherwise returns `None` and
+    /// leaves the game unchanged.
+    pub fn claim_draw_with(&mut self, m: &Move) -> Option<Termination> {
+        if self.is_game_over() {
+            return None;
+        }
+
+        let claimed = self.claimable_draw_with(m);
+        if let Some(termination) = claimed {
+            self.play_unchecked(m);
+            self.ended = Some((termination, Outcome::Draw));
+        }
+        claimed
+    }
+
+    /// Ends the game because `color` ran out of time. The opponent wins,
+    /// unless they have insufficient material to checkmate, in which case
+    /// the game is drawn. Returns `None` if the game is already over.
+    pub fn time_forfeit(&mut self, color: Color) -> Option<Outcome> {
+        if self.is_game_over() {
+            return None;
+        }
+
+        let outcome = if self.position().has_insufficient_material(!color) {
+            Outcome::Draw
+        } else {
+            Outcome::Decisive { winner: !color }
+        };
+
+        self.ended = Some((Termination::TimeForfeit, outcome.clone()));
+        Some(outcome)
+    }
+
+    /// Ends the game with a loss for `color`, because they broke the rules.
+    /// Returns `None` if the game is already over.
+    pub fn rules_infraction(&mut self, color: Color) -> Option<Outcome> {
+        if self.is_game_over() {
+            return None;
+        }
+
+        let outcome = Outcome::Decisive { winner: !color };
+        self.ended = Some((Termination::RulesInfraction, outcome.clone()));
+        Some(outcome)
+    }
+}
+
+impl<P: Position + Clone + Default> Default for Game<P> {
+    fn default() -> Game<P> {
+        Game::new(P::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use position::Chess;
+    use fen::Fen;
+    use uci::Uci;
+    use types::{White, Black};
+
+    fn play_ucis(game: &mut Game<Chess>, ucis: &[&str]) {
+        for uci in ucis {
+            let uci: Uci = uci.parse().expect("valid uci");
+            let m = uci.to_move(game.position()).expect("legal move");
+            game.play(&m).expect("legal move");
+        }
+    }
+
+    #[test]
+    fn test_fivefold_repetition() {
+        let mut game: Game<Chess> = Game::default();
+
+        for _ in 0..4 {
+            assert_eq!(game.outcome(), None);
+            play_ucis(&mut game, &["b1c3", "b8c6", "c3b1", "c6b8"]);
+        }
+
+        assert_eq!(game.repetitions(), 5);
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+        assert_eq!(game.termination(), Some(Termination::FivefoldRepetition));
+
+        game.undo();
+        assert_eq!(game.repetitions(), 4);
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.moves().len(), 15);
+    }
+
+    #[test]
+    fn test_repetition_key_pockets() {
+        let a: Fen = "4k3/8/8/8/8/8/8/4K3[N] w - - 0 1".parse().expect("valid fen");
+        let b: Fen = "4k3/8/8/8/8/8/8/4K3[n] w - - 0 1".parse().expect("valid fen");
+        assert_eq!(zobrist::hash(&a), zobrist::hash(&b));
+        assert!(RepetitionKey::new(&a) != RepetitionKey::new(&b));
+    }
+
+    #[test]
+    fn test_seventyfive_moves() {
+        let fen: Fen = "8/8/4k3/8/8/3K4/8/7R w - - 149 100".parse().expect("valid fen");
+        let mut game = Game::<Chess>::new(fen.position().expect("legal position"));
+        assert!(!game.is_game_over());
+
+        play_ucis(&mut game, &["h1h2"]);
+        assert!(game.is_seventyfive_moves());
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+
+        // Checkmate takes precedence.
+        let fen: Fen = "7k/8/6K1/8/8/8/8/R7 w - - 149 100".parse().expect("valid fen");
+        let mut game = Game::<Chess>::new(fen.position().expect("legal position"));
+        play_ucis(&mut game, &["a1a8"]);
+        assert_eq!(game.outcome(), Some(Outcome::Decisive { winner: White }));
+        assert_eq!(game.termination(), Some(Termination::Checkmate));
+    }
+
+    #[test]
+    fn test_claim_draw() {
+        let mut game: Game<Chess> = Game::default();
+        play_ucis(&mut game, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert_eq!(game.claimable_draw(), None);
+        assert_eq!(game.claim_draw(), None);
+
+        play_ucis(&mut game, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert_eq!(game.claimable_draw(), Some(Termination::ThreefoldRepetition));
+        assert_eq!(game.outcome(), None);
+
+        assert_eq!(game.claim_draw(), Some(Termination::ThreefoldRepetition));
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+        assert_eq!(game.termination().map(|t| t.pgn()), Some("normal"));
+
+        game.undo();
+        assert_eq!(game.termination(), None);
+
+        let fen: Fen = "8/8/4k3/8/8/3K4/8/7R b - - 100 80".parse().expect("valid fen");
+        let game = Game::<Chess>::new(fen.position().expect("legal position"));
+        assert_eq!(game.claimable_draw(), Some(Termination::FiftyMoves));
+    }
+
+    #[test]
+    fn test_claim_draw_with() {
+        let mut game: Game<Chess> = Game::default();
+        play_ucis(&mut game, &["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"]);
+        assert_eq!(game.claimable_draw(), None);
+
+        let m = "f6g8".parse::<Uci>().expect("valid uci").to_move(game.position()).expect("legal move");
+        let other = "f6h5".parse::<Uci>().expect("valid uci").to_move(game.position()).expect("legal move");
+        assert_eq!(game.claim_draw_with(&other), None);
+        assert_eq!(game.moves().len(), 7);
+
+        assert_eq!(game.claim_draw_with(&m), Some(Termination::ThreefoldRepetition));
+        assert_eq!(game.moves().len(), 8);
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+
+        let fen: Fen = "8/8/4k3/8/8/3K4/8/7R w - - 99 80".parse().expect("valid fen");
+        let game = Game::<Chess>::new(fen.position().expect("legal position"));
+        let m = "h1h2".parse::<Uci>().expect("valid uci").to_move(game.position()).expect("legal move");
+        assert_eq!(game.claimable_draw(), None);
+        assert_eq!(game.claimable_draw_with(&m), Some(Termination::FiftyMoves));
+    }
+
+    #[test]
+    fn test_time_forfeit() {
+        let mut game: Game<Chess> = Game::default();
+        assert_eq!(game.time_forfeit(Black), Some(Outcome::Decisive { winner: White }));
+        assert_eq!(game.termination(), Some(Termination::TimeForfeit));
+        assert_eq!(game.termination().map(|t| t.pgn()), Some("time forfeit"));
+        assert_eq!(game.time_forfeit(White), None);
+
+        // White can not checkmate with a lone king.
+        let fen: Fen = "8/8/4k3/8/8/3K4/8/7r w - - 0 1".parse().expect("valid fen");
+        let mut game = Game::<Chess>::new(fen.position().expect("legal position"));
+        assert_eq!(game.time_forfeit(Black), Some(Outcome::Draw));
+
+        let mut game: Game<Chess> = Game::default();
+        assert_eq!(game.rules_infraction(White), Some(Outcome::Decisive { winner: Black }));
+        assert_eq!(game.termination().map(|t| t.pgn()), Some("rules infraction"));
+    }
+}
@@ -91,18 +91,27 @@ mod setup;
 mod movelist;
 mod magics;
 mod perft;
+mod game;
 
 pub mod square;
 pub mod attacks;
 pub mod fen;
 pub mod uci;
 pub mod san;
+pub mod betza;
+pub mod variants;
+pub mod zobrist;
+pub mod clock;
+pub mod dests;
+pub mod premove;
+pub mod packed;
 
 pub use square::Square;
-pub use types::{Color, Role, Piece, Move, Pocket, Pockets, RemainingChecks};
+pub use types::{Color, Role, Piece, Move, Pocket, Pockets, Hostages, RemainingChecks};
 pub use bitboard::{Bitboard, CarryRippler};
-pub use board::{Board, Pieces};
+pub use board::{Board, Pieces, AttackMap};
 pub use setup::{Setup, CastlingSide};
 pub use movelist::MoveList;
-pub use position::{IllegalMove, Outcome, Position, PositionError, Chess};
+pub use position::{IllegalMove, IllegalMoveReason, Outcome, Termination, Position, PositionError, Chess, Undo, MoveGen, Rules, Custom, CustomState};
 pub use perft::perft;
+pub use game::Game;
@@ -0,0 +1,202 @@
+// This file is part of the shakmaty library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Moves packed into 16 bits, for transposition tables and game storage.
+//!
+//! The lowest 6 bits hold the target square, the next 6 bits the origin
+//! square and the highest 4 bits the kind of move. Roles and captures are
+//! not stored, but recovered from the position the move is played in.
+//!
+//! # Examples
+//!
+//! ```
+//! use shakmaty::{Chess, Position};
+//! use shakmaty::packed::PackedMove;
+//!
+//! let pos = Chess::default();
+//!
+//! for m in pos.legals().iter() {
+//!     let packed = PackedMove::from(m);
+//!     assert_eq!(packed.to_move(&pos).as_ref(), Some(m));
+//! }
+//! ```
+
+use square::Square;
+use types::{Move, Role, ROLES};
+use setup::Setup;
+
+const NORMAL: u16 = 0;
+const EN_PASSANT: u16 = 1;
+const CASTLE: u16 = 2;
+const NULL: u16 = 3;
+const PUT: u16 = 4;
+const EXCHANGE: u16 = 5;
+const PROMOTION: u16 = 8;
+
+/// A `Move` packed into 16 bits.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct PackedMove(u16);
+
+/// A container for packed moves, for example the moves of a game.
+pub type PackedMoveList = Vec<PackedMove>;
+
+impl PackedMove {
+    fn new(kind: u16, from: u16, to: Square) -> PackedMove {
+        PackedMove(kind << 12 | from << 6 | to.index() as u16)
+    }
+
+    /// Unpacks raw bits, for example from a transposition table entry.
+    pub fn from_bits(bits: u16) -> PackedMove {
+        PackedMove(bits)
+    }
+
+    /// The raw bits.
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    fn kind(self) -> u16 {
+        self.0 >> 12
+    }
+
+    fn origin_bits(self) -> u16 {
+        (self.0 >> 6) & 63
+    }
+
+    fn to_square(self) -> Square {
+        Square::new((self.0 & 63) as i8)
+    }
+
+    /// Restores the `Move` in the position it was packed in.
+    ///
+    /// Returns `None` if the bits are invalid or there is no piece on the
+    /// origin square. The move is not checked for legality, use
+    /// `Position::is_legal()` or `Position::is_pseudo_legal()` for moves
+    /// from untrusted sources such as transposition tables.
+    pub fn to_move<S: Setup>(&self, setup: &S) -> Option<Move> {
+        let from = Square::new(self.origin_bits() as i8);
+        let to = self.to_square();
+
+        match self.kind() {
+            NORMAL => Some(Move::Normal {
+                role: setup.board().role_at(from)?,
+                from,
+                capture: setup.board().role_at(to),
+                to,
+                promotion: None,
+            }),
+            EN_PASSANT => Some(Move::EnPassant { from, to }),
+            CASTLE => Some(Move::Castle { king: from, rook: to }),
+            NULL if self.0 == NULL << 12 => Some(Move::Null),
+            PUT => Some(Move::Put { role: role(self.origin_bits())?, to }),
+            EXCHANGE => Some(Move::Exchange {
+                hostage: role(self.origin_bits() >> 3)?,
+                role: role(self.origin_bits() & 7)?,
+                to,
+            }),
+            kind if kind > PROMOTION => Some(Move::Normal {
+                role: setup.board().role_at(from)?,
+                from,
+                capture: setup.board().role_at(to),
+                to,
+                promotion: Some(role(kind - PROMOTION)?),
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn role(index: u16) -> Option<Role> {
+    ROLES.get(index as usize).cloned()
+}
+
+impl<'a> From<&'a Move> for PackedMove {
+    fn from(m: &'a Move) -> PackedMove {
+        match *m {
+            Move::Normal { from, to, promotion: None, .. } =>
+                PackedMove::new(NORMAL, from.index() as u16, to),
+            Move::Normal { from, to, promotion: Some(promotion), .. } =>
+                PackedMove::new(PROMOTION + promotion as u16, from.index() as u16, to),
+            Move::EnPassant { from, to } =>
+                PackedMove::new(EN_PASSANT, from.index() as u16, to),
+            Move::Castle { king, rook } =>
+                PackedMove::new(CASTLE, king.index() as u16, rook),
+            Move::Put { role, to } =>
+                PackedMove::new(PUT, role as u16, to),
+            Move::Exchange { hostage, role, to } =>
+                PackedMove::new(EXCHANGE, (hostage as u16) << 3 | role as u16, to),
+            Move::Null =>
+                PackedMove(NULL << 12),
+        }
+    }
+}
+
+impl From<Move> for PackedMove {
+    fn from(m: Move) -> PackedMove {
+        (&m).into()
+    }
+}
+
+/// Packs a sequence of moves, no matter how long.
+pub fn pack(moves: &[Move]) -> PackedMoveList {
+    moves.iter().map(PackedMove::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use square;
+    use fen::Fen;
+    use position::{Position, Chess};
+
+    #[test]
+    fn test_roundtrip() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "8/8/8/8/k2pP2Q/8/8/3K4 b - e3 0 1",
+        ];
+
+        for fen in &fens {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+
+            for m in pos.legals().iter() {
+                let packed = PackedMove::from(m);
+                assert_eq!(PackedMove::from_bits(packed.bits()), packed);
+                assert_eq!(packed.to_move(&pos).as_ref(), Some(m));
+            }
+        }
+    }
+
+    #[test]
+    fn test_drops_and_null() {
+        let pos: Fen = "4k3/8/8/8/8/8/8/4K3[Qn] w - - 0 1".parse().expect("valid fen");
+
+        let m = Move::Put { role: Role::Queen, to: square::D4 };
+        assert_eq!(PackedMove::from(&m).to_move(&pos), Some(m));
+        assert_eq!(PackedMove::from(Move::Null).to_move(&pos), Some(Move::Null));
+
+        let m = Move::Exchange { hostage: Role::Knight, role: Role::Rook, to: square::A1 };
+        assert_eq!(PackedMove::from(&m).to_move(&pos), Some(m));
+
+        assert_eq!(PackedMove::from_bits(0x3001).to_move(&pos), None);
+        assert_eq!(pack(&[Move::Null, Move::Null]).len(), 2);
+        assert_eq!(pack(&vec![Move::Null; 2000]).len(), 2000);
+    }
+}
@@ -38,19 +38,17 @@ use uci::Uci;
 pub fn perft<P: Position + Clone>(pos: &P, depth: u8) -> usize {
     if depth < 1 {
         1
+    } else if depth == 1 {
+        pos.count_legal_moves()
     } else {
         let mut moves = MoveList::new();
         pos.legal_moves(&mut moves);
 
-        if depth == 1 {
-            moves.len()
-        } else {
-            moves.drain(..).map(|m| {
-                let mut child = pos.clone();
-                child.play_unchecked(&m);
-                perft(&child, depth - 1)
-            }).sum()
-        }
+        moves.drain(..).map(|m| {
+            let mut child = pos.clone();
+            child.play_unchecked(&m);
+            perft(&child, depth - 1)
+        }).sum()
     }
 }
 
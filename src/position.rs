@@ -20,14 +20,18 @@ use bitboard;
 use bitboard::Bitboard;
 use square;
 use square::Square;
-use types::{Color, White, Black, Role, Piece, Move, Pockets, RemainingChecks};
+use types::{Color, White, Black, Role, Piece, Move, Pockets, RemainingChecks, ROLES};
 use setup::{Setup, Castling, CastlingSide};
 use movelist::{MoveList, ArrayVecExt};
+use zobrist;
 
 use option_filter::OptionFilterExt;
 
 use std::fmt;
 use std::error::Error;
+use std::mem;
+use std::cmp::max;
+use std::hash::{Hash, Hasher};
 
 /// Outcome of a game.
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -46,6 +50,85 @@ impl fmt::Display for Outcome {
     }
 }
 
+/// Reason for the end of a game.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    /// Neither side can checkmate by any sequence of legal moves, even
+    /// though there is enough material.
+    DeadPosition,
+    /// Variant specific win, loss or draw.
+    VariantEnd,
+    /// Automatic draw after 75 moves without pawn move or capture.
+    SeventyFiveMoves,
+    /// Automatic draw after the fifth occurence of a position.
+    FivefoldRepetition,
+    /// Claimed draw after 50 moves without pawn move or capture.
+    FiftyMoves,
+    /// Claimed draw after the third occurence of a position.
+    ThreefoldRepetition,
+    /// A player ran out of time.
+    TimeForfeit,
+    /// A player forfeited by breaking the rules, for example with an illegal
+    /// move in blitz.
+    RulesInfraction,
+}
+
+impl Termination {
+    fn desc(&self) -> &str {
+        match *self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::DeadPosition => "dead position",
+            Termination::VariantEnd => "variant end",
+            Termination::SeventyFiveMoves => "75-move rule",
+            Termination::FivefoldRepetition => "fivefold repetition",
+            Termination::FiftyMoves => "50-move rule",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::RulesInfraction => "rules infraction",
+        }
+    }
+
+    /// Tests if the draw had to be claimed by a player.
+    pub fn is_claimed(&self) -> bool {
+        match *self {
+            Termination::FiftyMoves | Termination::ThreefoldRepetition => true,
+            _ => false,
+        }
+    }
+
+    /// The value of the PGN `Termination` tag.
+    ///
+    /// Games ended by the rules of chess are recorded as `normal`. The
+    /// result itself goes into the `Result` tag, which is the `Display` of
+    /// `Outcome`.
+    pub fn pgn(&self) -> &'static str {
+        match *self {
+            Termination::TimeForfeit => "time forfeit",
+            Termination::RulesInfraction => "rules infraction",
+            Termination::Checkmate |
+                Termination::Stalemate |
+                Termination::InsufficientMaterial |
+                Termination::DeadPosition |
+                Termination::VariantEnd |
+                Termination::SeventyFiveMoves |
+                Termination::FivefoldRepetition |
+                Termination::FiftyMoves |
+                Termination::ThreefoldRepetition => "normal",
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.desc().fmt(f)
+    }
+}
+
 /// Reasons for a `Setup` not beeing a legal `Position`.
 #[derive(Debug)]
 pub enum PositionError {
@@ -93,13 +176,76 @@ impl Error for PositionError {
     }
 }
 
+/// Reasons for a move to be illegal.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum IllegalMoveReason {
+    /// There is no piece (of the given role) on the origin square.
+    NoPiece,
+    /// The piece belongs to the side not to move.
+    WrongColor,
+    /// The piece can not move like that.
+    InvalidMovement,
+    /// The move leaves or puts the own king in check.
+    KingInCheck,
+    /// The piece is pinned to the own king.
+    Pinned,
+    /// The king is in check or would pass or land on an attacked square.
+    CastlingThroughCheck,
+    /// There are no castling rights for that rook.
+    NoCastlingRights,
+    /// A pawn move to the backrank is missing the promotion.
+    PromotionMissing,
+    /// The move has a promotion that is not allowed.
+    PromotionNotAllowed,
+    /// The move is forbidden by a rule specific to the variant.
+    VariantRule,
+}
+
+impl IllegalMoveReason {
+    fn desc(&self) -> &str {
+        match *self {
+            IllegalMoveReason::NoPiece => "no piece on origin square",
+            IllegalMoveReason::WrongColor => "not the side to move",
+            IllegalMoveReason::InvalidMovement => "piece can not move like that",
+            IllegalMoveReason::KingInCheck => "leaves king in check",
+            IllegalMoveReason::Pinned => "piece is pinned",
+            IllegalMoveReason::CastlingThroughCheck => "castling through check",
+            IllegalMoveReason::NoCastlingRights => "no castling rights",
+            IllegalMoveReason::PromotionMissing => "promotion missing",
+            IllegalMoveReason::PromotionNotAllowed => "promotion not allowed",
+            IllegalMoveReason::VariantRule => "forbidden by variant rules",
+        }
+    }
+}
+
+impl fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.desc().fmt(f)
+    }
+}
+
 /// Error in case of illegal moves.
-#[derive(Debug)]
-pub struct IllegalMove;
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct IllegalMove {
+    reason: IllegalMoveReason,
+}
+
+impl IllegalMove {
+    /// Why the move is illegal.
+    pub fn reason(&self) -> IllegalMoveReason {
+        self.reason
+    }
+}
+
+impl From<IllegalMoveReason> for IllegalMove {
+    fn from(reason: IllegalMoveReason) -> IllegalMove {
+        IllegalMove { reason }
+    }
+}
 
 impl fmt::Display for IllegalMove {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        "illegal move".fmt(f)
+        write!(f, "illegal move: {}", self.reason)
     }
 }
 
@@ -131,9 +277,91 @@ pub trait Position: Setup {
     /// # Panics
     ///
     /// Panics if `moves` is too full. This can not happen if an empty
-    /// `MoveList` is passed.
+    /// `MoveList` is passed, except in `Hostage` and `Fairy` positions with
+    /// more legal moves than a `MoveList` can hold.
     fn legal_moves(&self, moves: &mut MoveList);
 
+    /// Counts the legal moves.
+    ///
+    /// Defaults to the length of `legal_moves()`.
+    fn count_legal_moves(&self) -> usize {
+        let mut moves = MoveList::new();
+        self.legal_moves(&mut moves);
+        moves.len()
+    }
+
+    /// Collects the legal moves to squares in `target`. For castling moves
+    /// the target square is the rook square, for en passant captures it is
+    /// the en passant square.
+    ///
+    /// Defaults to filtering `legal_moves()`.
+    fn target_moves(&self, target: Bitboard, moves: &mut MoveList) {
+        self.legal_moves(moves);
+        moves.swap_retain(|m| m.to_opt().map_or(false, |to| target.contains(to)));
+    }
+
+    /// Collects the legal moves of the piece on `from`. Drops and null moves
+    /// are never included.
+    ///
+    /// Defaults to filtering `legal_moves()`.
+    fn legal_moves_from(&self, from: Square, moves: &mut MoveList) {
+        self.legal_moves(moves);
+        moves.swap_retain(|m| m.from() == Some(from));
+    }
+
+    /// Collects the legal captures, including en passant.
+    ///
+    /// Defaults to filtering `legal_moves()`.
+    fn capture_moves(&self, moves: &mut MoveList) {
+        self.legal_moves(moves);
+        moves.swap_retain(|m| m.capture().is_some());
+    }
+
+    /// Collects the legal moves that are not captures, including castling
+    /// and drops.
+    ///
+    /// Defaults to filtering `legal_moves()`.
+    fn quiet_moves(&self, moves: &mut MoveList) {
+        self.legal_moves(moves);
+        moves.swap_retain(|m| m.capture().is_none());
+    }
+
+    /// Collects the legal promotions, with or without capture.
+    ///
+    /// Defaults to filtering `legal_moves()`.
+    fn promotion_moves(&self, moves: &mut MoveList) {
+        self.legal_moves(moves);
+        moves.swap_retain(|m| m.promotion().is_some());
+    }
+
+    /// Collects pseudo-legal moves in an existing buffer. These are the legal
+    /// moves, plus moves that may leave the king in check. Use
+    /// `is_legal_pseudo()` to filter them.
+    ///
+    /// Defaults to `legal_moves()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `moves` is too full. This can not happen if an empty
+    /// `MoveList` is passed.
+    fn pseudo_legal_moves(&self, moves: &mut MoveList) {
+        self.legal_moves(moves);
+    }
+
+    /// Tests if a move generated by `pseudo_legal_moves()` is legal.
+    fn is_legal_pseudo(&self, _m: &Move) -> bool {
+        true
+    }
+
+    /// Tests if a move would be generated by `pseudo_legal_moves()`, without
+    /// generating all moves. Useful to validate moves from a transposition
+    /// table. Like `is_legal()`, accepts null moves when not in check.
+    ///
+    /// Defaults to `is_legal()`.
+    fn is_pseudo_legal(&self, m: &Move) -> bool {
+        self.is_legal(m)
+    }
+
     /// Generates a subset of legal moves: All piece moves and drops of type
     /// `role` to the square `to`, excluding castling moves.
     ///
@@ -173,21 +401,40 @@ pub trait Position: Setup {
                 self.castling_moves(CastlingSide::KingSide, &mut moves),
             Move::Castle { .. } =>
                 self.castling_moves(CastlingSide::QueenSide, &mut moves),
+            Move::Exchange { .. } =>
+                self.legal_moves(&mut moves),
+            Move::Null =>
+                return self.checkers().is_empty(),
         }
         moves.contains(m)
     }
 
+    /// Tests if a move is legal, explaining why not otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the reason if the move is not legal.
+    fn validate(&self, m: &Move) -> Result<(), IllegalMove> {
+        if self.is_legal(m) {
+            Ok(())
+        } else {
+            Err(illegal_move_reason(self, m).into())
+        }
+    }
+
     /// Tests if a move is irreversible.
     ///
     /// In standard chess pawn moves, captures and moves that destroy castling
-    /// rights are irreversible.
+    /// rights are irreversible. Null moves are reversible.
     fn is_irreversible(&self, m: &Move) -> bool {
         match *m {
             Move::Normal { role: Role::Pawn, .. } |
                 Move::Normal { capture: Some(_), .. } |
                 Move::Castle { .. } |
                 Move::EnPassant { .. } |
-                Move::Put { .. } => true,
+                Move::Put { .. } |
+                Move::Exchange { .. } => true,
+            Move::Null => false,
             Move::Normal { role, from, to, .. } =>
                 self.castling_rights().contains(from) ||
                 self.castling_rights().contains(to) ||
@@ -210,6 +457,41 @@ pub trait Position: Setup {
             .map_or(Bitboard(0), |king| self.king_attackers(king, !self.turn(), self.board().occupied()))
     }
 
+    /// Tests if a legal move gives check.
+    ///
+    /// Defaults to playing the move on a copy of the position.
+    fn gives_check(&self, m: &Move) -> bool where Self: Clone {
+        let mut pos = self.clone();
+        pos.play_unchecked(m);
+        pos.checkers().any()
+    }
+
+    /// Collects the legal moves that give check.
+    fn check_moves(&self, moves: &mut MoveList) where Self: Clone {
+        self.legal_moves(moves);
+        moves.swap_retain(|m| self.gives_check(m));
+    }
+
+    /// Static exchange evaluation of a move, in pawns: the material balance
+    /// after the best sequence of captures on the target square, with both
+    /// sides free to stop capturing. Includes x-ray attackers and respects
+    /// absolute pins.
+    ///
+    /// The move is assumed to be legal. Castling, drops and null moves are
+    /// evaluated as 0.
+    ///
+    /// Returns `None` if the variant does not support static exchange
+    /// evaluation, for example because its pieces move differently.
+    fn see(&self, _m: &Move) -> Option<i32> {
+        None
+    }
+
+    /// Tests if the static exchange evaluation of a move is at least
+    /// `threshold`.
+    fn see_ge(&self, m: &Move, threshold: i32) -> Option<bool> {
+        self.see(m).map(|value| value >= threshold)
+    }
+
     /// Checks if the game is over due to a special variant end condition.
     ///
     /// Note that for example stalemate is not considered a variant-specific
@@ -242,6 +524,29 @@ pub trait Position: Setup {
     /// Tests for insufficient winning material.
     fn is_insufficient_material(&self) -> bool;
 
+    /// Tests if `color` can not possibly checkmate the opponent by any
+    /// legal sequence of moves. This is what matters for example when the
+    /// opponent runs out of time.
+    ///
+    /// Defaults to `is_insufficient_material()`, which is correct but
+    /// conservative.
+    fn has_insufficient_material(&self, _color: Color) -> bool {
+        self.is_insufficient_material()
+    }
+
+    /// Tests if no sequence of legal moves can lead to checkmate.
+    ///
+    /// Implementations may only detect some dead positions. Defaults to
+    /// `is_insufficient_material()`.
+    ///
+    /// Dead positions are not part of `outcome()`, `termination()` and
+    /// `is_game_over()`, because detecting them can be expensive. Use
+    /// `outcome_with_dead_position()` and
+    /// `termination_with_dead_position()` to include them.
+    fn is_dead_position(&self) -> bool {
+        self.is_insufficient_material()
+    }
+
     /// Tests if the game is over due to checkmate, stalemate, insufficient
     /// material or variant end.
     fn is_game_over(&self) -> bool {
@@ -266,6 +571,37 @@ pub trait Position: Setup {
         })
     }
 
+    /// Like `outcome()`, but also detects draws by dead positions.
+    fn outcome_with_dead_position(&self) -> Option<Outcome> {
+        self.outcome().or_else(|| {
+            if self.is_dead_position() { Some(Outcome::Draw) } else { None }
+        })
+    }
+
+    /// The reason the game is over, or `None` if the game is not over.
+    /// Consistent with `outcome()`.
+    fn termination(&self) -> Option<Termination> {
+        if self.variant_outcome().is_some() {
+            Some(Termination::VariantEnd)
+        } else if self.is_checkmate() {
+            Some(Termination::Checkmate)
+        } else if self.is_stalemate() {
+            Some(Termination::Stalemate)
+        } else if self.is_insufficient_material() {
+            Some(Termination::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+
+    /// Like `termination()`, but also detects dead positions. Consistent
+    /// with `outcome_with_dead_position()`.
+    fn termination_with_dead_position(&self) -> Option<Termination> {
+        self.termination().or_else(|| {
+            if self.is_dead_position() { Some(Termination::DeadPosition) } else { None }
+        })
+    }
+
     /// Plays a move.
     ///
     /// # Errors
@@ -274,12 +610,9 @@ pub trait Position: Setup {
     fn play(mut self, m: &Move) -> Result<Self, IllegalMove>
         where Self: Sized
     {
-        if self.is_legal(m) {
-            self.play_unchecked(m);
-            Ok(self)
-        } else {
-            Err(IllegalMove {})
-        }
+        self.validate(m)?;
+        self.play_unchecked(m);
+        Ok(self)
     }
 
     /// Plays a move. It is the callers responsibility to ensure the move is
@@ -301,19 +634,654 @@ pub struct Chess {
     ep_square: Option<Square>,
     halfmove_clock: u32,
     fullmoves: u32,
+    zobrist: u64,
 }
 
 impl Default for Chess {
     fn default() -> Chess {
-        Chess {
+        let mut pos = Chess {
             board: Board::default(),
             turn: White,
             castling: Castling::default(),
             ep_square: None,
             halfmove_clock: 0,
             fullmoves: 1,
+            zobrist: 0,
+        };
+        pos.rehash();
+        pos
+    }
+}
+
+/// Positions are equal if they are the same position in the sense of the
+/// FIDE rules on repetition: the same pieces on the same squares, the same
+/// side to move, the same castling rights and the same possible en passant
+/// captures.
+///
+/// Move counters are ignored, and so is an en passant square if no en
+/// passant capture is legal. Compare `fen::fen()` of both positions to
+/// include them.
+impl PartialEq for Chess {
+    fn eq(&self, other: &Chess) -> bool {
+        self.turn == other.turn &&
+        self.board.white() == other.board.white() &&
+        ROLES.iter().all(|r| self.board.by_role(*r) == other.board.by_role(*r)) &&
+        self.castling_rights() == other.castling_rights() &&
+        self.ep_square() == other.ep_square()
+    }
+}
+
+impl Eq for Chess {}
+
+impl Hash for Chess {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.turn.hash(state);
+        self.board.white().hash(state);
+        for role in &ROLES {
+            self.board.by_role(*role).hash(state);
+        }
+        self.castling_rights().hash(state);
+        self.ep_square().hash(state);
+    }
+}
+
+/// Information needed to take back a move with `Chess::unplay()`.
+#[derive(Clone, Debug)]
+pub struct Undo {
+    castling: Castling,
+    ep_square: Option<Square>,
+    halfmove_clock: u32,
+    zobrist: u64,
+    promoted: bool,
+    capture_promoted: bool,
+}
+
+impl Chess {
+    /// The Polyglot key of the position, updated incrementally as moves are
+    /// played.
+    ///
+    /// Unlike `zobrist::hash()` of the position, the en passant file is
+    /// included whenever a pawn stands next to the double stepped pawn, even
+    /// if the en passant capture is not legal. This is compatible with
+    /// Polyglot and with `zobrist::hash()` of the `Fen`.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.zobrist
+    }
+
+    fn rehash(&mut self) {
+        self.zobrist = zobrist::hash_parts(&self.board, self.turn, self.castling.castling_rights(), self.ep_square);
+    }
+
+    /// Like `play_unchecked()`, but returns the information required to
+    /// restore the position with `unplay()`. Useful for searching without
+    /// copying the position at every node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use shakmaty::{Chess, Position, Setup};
+    /// let mut pos = Chess::default();
+    /// let m = pos.legals()[0].clone();
+    ///
+    /// let undo = pos.play_undoable(&m);
+    /// assert_eq!(pos.turn(), shakmaty::Color::Black);
+    ///
+    /// pos.unplay(&m, undo);
+    /// assert_eq!(pos.turn(), shakmaty::Color::White);
+    /// ```
+    pub fn play_undoable(&mut self, m: &Move) -> Undo {
+        let undo = Undo {
+            castling: self.castling.clone(),
+            ep_square: self.ep_square,
+            halfmove_clock: self.halfmove_clock,
+            zobrist: self.zobrist,
+            promoted: m.from().map_or(false, |from| self.board.promoted().contains(from)),
+            capture_promoted: m.to_opt().map_or(false, |to| self.board.promoted().contains(to)),
+        };
+
+        self.play_unchecked(m);
+        undo
+    }
+
+    /// Takes back a move played with `play_undoable()`, restoring the
+    /// position exactly.
+    ///
+    /// # Panics
+    ///
+    /// Undoing anything but the last move played can corrupt the state of
+    /// the position and may (or may not) panic.
+    pub fn unplay(&mut self, m: &Move, undo: Undo) {
+        let color = !self.turn;
+
+        match *m {
+            Move::Normal { role, from, capture, to, .. } => {
+                self.board.discard_piece_at(to);
+                self.board.set_piece_at(from, role.of(color), undo.promoted);
+
+                if let Some(capture) = capture {
+                    self.board.set_piece_at(to, capture.of(!color), undo.capture_promoted);
+                }
+            },
+            Move::Castle { king, rook } => {
+                let side = if rook - king < 0 { CastlingSide::QueenSide } else { CastlingSide::KingSide };
+                self.board.discard_piece_at(side.king_to(color));
+                self.board.discard_piece_at(side.rook_to(color));
+                self.board.set_piece_at(king, color.king(), false);
+                self.board.set_piece_at(rook, color.rook(), false);
+            },
+            Move::EnPassant { from, to } => {
+                self.board.discard_piece_at(to);
+                self.board.set_piece_at(from, color.pawn(), false);
+                self.board.set_piece_at(to.combine(from), (!color).pawn(), false);
+            },
+            Move::Put { to, .. } | Move::Exchange { to, .. } => {
+                self.board.discard_piece_at(to);
+            },
+            Move::Null => (),
+        }
+
+        if color.is_black() {
+            self.fullmoves -= 1;
+        }
+
+        self.turn = color;
+        self.castling = undo.castling;
+        self.ep_square = undo.ep_square;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.zobrist = undo.zobrist;
+    }
+
+    /// Iterates over the legal moves in stages, for move ordering in search.
+    /// Each stage is only generated once the previous one is exhausted.
+    ///
+    /// The stages are: the hash move (if legal), winning and equal captures
+    /// (most valuable victim first), quiet promotions, quiet moves and
+    /// finally losing captures. When in check, all evasions are generated
+    /// at once after the hash move.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use shakmaty::{Chess, Position};
+    /// let pos = Chess::default();
+    /// assert_eq!(pos.move_gen(None).count(), 20);
+    /// ```
+    pub fn move_gen(&self, hash_move: Option<Move>) -> MoveGen {
+        let king = self.board().king_of(self.turn()).expect("king in standard chess");
+
+        MoveGen {
+            pos: self,
+            king,
+            blockers: self.board().slider_blockers(self.them(), king),
+            hash_move,
+            stage: Stage::Begin,
+            moves: MoveList::new(),
+            index: 0,
+            losing: MoveList::new(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Stage {
+    Begin,
+    HashMove,
+    Captures,
+    Promotions,
+    Quiets,
+    LosingCaptures,
+    Done,
+}
+
+/// A staged generator of legal moves. See `Chess::move_gen()`.
+pub struct MoveGen<'a> {
+    pos: &'a Chess,
+    king: Square,
+    blockers: Bitboard,
+    hash_move: Option<Move>,
+    stage: Stage,
+    moves: MoveList,
+    index: usize,
+    losing: MoveList,
+}
+
+impl<'a> MoveGen<'a> {
+    fn retain_safe(&mut self) {
+        let (pos, king, blockers) = (self.pos, self.king, self.blockers);
+        self.moves.retain(|m| is_safe(pos, king, m, blockers));
+    }
+
+    fn next_stage(&mut self) {
+        let pos = self.pos;
+        self.moves.clear();
+        self.index = 0;
+
+        self.stage = match self.stage {
+            Stage::Begin | Stage::HashMove => {
+                let checkers = pos.checkers();
+                if checkers.any() {
+                    evasions(pos, self.king, checkers, &mut self.moves);
+                    gen_en_passant(pos.board(), pos.turn(), pos.ep_square, &mut self.moves);
+                    self.retain_safe();
+                    Stage::LosingCaptures // nothing left after the evasions
+                } else {
+                    gen_en_passant(pos.board(), pos.turn(), pos.ep_square, &mut self.moves);
+                    gen_non_king(pos, pos.them(), &mut self.moves);
+                    gen_safe_king(pos, self.king, pos.them(), &mut self.moves);
+                    self.retain_safe();
+
+                    self.moves.sort_by_key(|m| capture_order(m));
+                    for m in self.moves.iter().filter(|m| !is_winning_capture(pos, m)) {
+                        self.losing.push(m.clone());
+                    }
+                    self.moves.retain(|m| is_winning_capture(pos, m));
+                    Stage::Captures
+                }
+            },
+            Stage::Captures => {
+                let target = !pos.board().occupied() & bitboard::BACKRANKS;
+                gen_pawn_moves(pos, target, &mut self.moves);
+                self.retain_safe();
+                Stage::Promotions
+            },
+            Stage::Promotions => {
+                let target = !pos.board().occupied();
+                gen_pawn_moves(pos, target & !bitboard::BACKRANKS, &mut self.moves);
+                KnightTag::gen_moves(pos, target, &mut self.moves);
+                BishopTag::gen_moves(pos, target, &mut self.moves);
+                RookTag::gen_moves(pos, target, &mut self.moves);
+                QueenTag::gen_moves(pos, target, &mut self.moves);
+                gen_safe_king(pos, self.king, target, &mut self.moves);
+                gen_castling_moves(pos, self.king, CastlingSide::KingSide, &mut self.moves);
+                gen_castling_moves(pos, self.king, CastlingSide::QueenSide, &mut self.moves);
+                self.retain_safe();
+                Stage::Quiets
+            },
+            Stage::Quiets => {
+                mem::swap(&mut self.moves, &mut self.losing);
+                Stage::LosingCaptures
+            },
+            Stage::LosingCaptures | Stage::Done => Stage::Done,
+        };
+    }
+}
+
+impl<'a> Iterator for MoveGen<'a> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        if self.stage == Stage::Begin {
+            // Yield the hash move before generating anything, so that no
+            // work is wasted if it causes a cutoff.
+            self.stage = Stage::HashMove;
+            self.hash_move = self.hash_move.take().filter(|m| {
+                self.pos.is_pseudo_legal(m) && self.pos.is_legal_pseudo(m)
+            });
+
+            if self.hash_move.is_some() {
+                return self.hash_move.clone();
+            }
+        }
+
+        while self.stage != Stage::Done {
+            while self.index < self.moves.len() {
+                let m = self.moves[self.index].clone();
+                self.index += 1;
+
+                if self.hash_move.as_ref() != Some(&m) {
+                    return Some(m);
+                }
+            }
+
+            self.next_stage();
+        }
+
+        None
+    }
+}
+
+fn piece_value(role: Role) -> u32 {
+    match role {
+        Role::Pawn => 1,
+        Role::Knight | Role::Bishop => 3,
+        Role::Rook => 5,
+        Role::Queen => 9,
+        Role::King => 100,
+    }
+}
+
+/// Most valuable victim first, then least valuable attacker.
+fn capture_order(m: &Move) -> u32 {
+    match *m {
+        Move::Normal { role, capture: Some(capture), .. } =>
+            1000 - 10 * piece_value(capture) + piece_value(role),
+        Move::EnPassant { .. } =>
+            1000 - 10 * piece_value(Role::Pawn) + piece_value(Role::Pawn),
+        _ => 1000,
+    }
+}
+
+/// Single and double pushes of a pawn of `turn` on `from`.
+fn pawn_pushes(turn: Color, from: Square, occupied: Bitboard) -> Bitboard {
+    let single = Bitboard::from_square(from).relative_shift(turn, 8) & !occupied;
+    let double = single.relative_shift(turn, 8) & Bitboard::relative_rank(turn, 3) & !occupied;
+    single | double
+}
+
+/// Squares a piece of `role` on `from` can reach by the standard rules of
+/// movement, ignoring checks, pins and own pieces on the target square.
+fn reachable(board: &Board, turn: Color, role: Role, from: Square) -> Bitboard {
+    match role {
+        Role::Pawn => attacks::pawn_attacks(turn, from) & board.by_color(!turn) |
+                      pawn_pushes(turn, from, board.occupied()),
+        _ => attacks::attacks(from, role.of(turn), board.occupied()),
+    }
+}
+
+/// Diagnoses a move that is known to be illegal.
+fn illegal_move_reason<P: Position + ?Sized>(pos: &P, m: &Move) -> IllegalMoveReason {
+    let turn = pos.turn();
+    let backrank = Bitboard::relative_rank(turn, 7);
+    let board = pos.board();
+    let our_king = board.king_of(turn);
+
+    let from = match *m {
+        Move::Normal { from, .. } | Move::EnPassant { from, .. } => from,
+        Move::Castle { king, .. } => king,
+        Move::Put { to, .. } | Move::Exchange { to, .. } => {
+            if pos.pockets().is_none() || board.occupied().contains(to) {
+                return IllegalMoveReason::InvalidMovement;
+            }
+            // The drop does not resolve a check.
+            return match our_king {
+                Some(king) if pos.king_attackers(king, !turn, board.occupied().with(to)).any() =>
+                    IllegalMoveReason::KingInCheck,
+                _ => IllegalMoveReason::VariantRule,
+            };
+        },
+        Move::Null => {
+            return if pos.checkers().any() {
+                IllegalMoveReason::KingInCheck
+            } else {
+                IllegalMoveReason::VariantRule
+            };
+        },
+    };
+
+    let piece = match board.piece_at(from) {
+        Some(piece) => piece,
+        None => return IllegalMoveReason::NoPiece,
+    };
+
+    if piece.color != turn {
+        return IllegalMoveReason::WrongColor;
+    }
+
+    let (to, occupied) = match *m {
+        Move::Normal { role, to, capture, promotion, .. } => {
+            if piece.role != role {
+                return IllegalMoveReason::NoPiece;
+            }
+            match promotion {
+                None if role == Role::Pawn && backrank.contains(to) =>
+                    return IllegalMoveReason::PromotionMissing,
+                Some(promotion) if role != Role::Pawn || !backrank.contains(to) ||
+                                   promotion == Role::Pawn || promotion == Role::King =>
+                    return IllegalMoveReason::PromotionNotAllowed,
+                _ => (),
+            }
+
+            if !reachable(board, turn, role, from).contains(to) ||
+               pos.us().contains(to) || capture != board.role_at(to) {
+                return IllegalMoveReason::InvalidMovement;
+            }
+
+            (to, board.occupied())
+        },
+        Move::EnPassant { to, .. } => {
+            if piece.role != Role::Pawn {
+                return IllegalMoveReason::NoPiece;
+            }
+            if pos.ep_square() != Some(to) || !attacks::pawn_attacks(turn, from).contains(to) {
+                return IllegalMoveReason::InvalidMovement;
+            }
+
+            (to, board.occupied().without(to.combine(from)))
+        },
+        Move::Castle { king, rook } => {
+            if piece.role != Role::King || !pos.castling_rights().contains(rook) {
+                return IllegalMoveReason::NoCastlingRights;
+            }
+            if pos.checkers().any() {
+                return IllegalMoveReason::CastlingThroughCheck;
+            }
+            let side = if king < rook { CastlingSide::KingSide } else { CastlingSide::QueenSide };
+            let king_to = side.king_to(turn);
+            let rook_to = side.rook_to(turn);
+            let occupied = board.occupied() ^ king ^ rook;
+            for sq in attacks::between(king, king_to).with(king_to) {
+                if pos.king_attackers(sq, !turn, occupied).any() {
+                    return IllegalMoveReason::CastlingThroughCheck;
+                }
+            }
+            let path = attacks::between(king, rook) | attacks::between(king, king_to).with(king_to) |
+                       attacks::between(rook, rook_to).with(rook_to);
+            return if (path & occupied).any() {
+                IllegalMoveReason::InvalidMovement
+            } else {
+                IllegalMoveReason::VariantRule
+            };
+        },
+        _ => return IllegalMoveReason::InvalidMovement,
+    };
+
+    // The piece can move like that. Find out if it really exposes the king.
+    let occupied = occupied.without(from).with(to);
+    let king = if piece.role == Role::King { Some(to) } else { our_king };
+    let exposed = match king {
+        Some(king) => pos.king_attackers(king, !turn, occupied) & occupied.without(to),
+        None => Bitboard(0),
+    };
+
+    if exposed.is_empty() {
+        IllegalMoveReason::VariantRule
+    } else if piece.role != Role::King && (exposed & !pos.checkers()).any() {
+        IllegalMoveReason::Pinned
+    } else {
+        IllegalMoveReason::KingInCheck
+    }
+}
+
+/// Captures that do not lose material by static exchange evaluation.
+fn is_winning_capture(pos: &Chess, m: &Move) -> bool {
+    m.promotion().is_some() || see_ge(pos, m, 0)
+}
+
+/// The state of an exchange on a single square.
+struct Exchange<'a> {
+    board: &'a Board,
+    turn: Color,
+    to: Square,
+    occupied: Bitboard,
+    attackers: Bitboard,
+    pins: [(Bitboard, Bitboard); 2],
+}
+
+impl<'a> Exchange<'a> {
+    /// Sets up the exchange after `m`. Returns the target square, the
+    /// material gained by `m` and the role that ends up on the target
+    /// square, or `None` if `m` is not a capture or a board move.
+    fn new<P: Setup + ?Sized>(pos: &'a P, m: &Move) -> Option<(Exchange<'a>, i32, Role)> {
+        let board = pos.board();
+
+        let (from, to, gain, on_square) = match *m {
+            Move::Normal { role, from, to, capture, promotion } => {
+                let mut gain = capture.map_or(0, piece_value) as i32;
+                if let Some(promotion) = promotion {
+                    gain += piece_value(promotion) as i32 - piece_value(Role::Pawn) as i32;
+                }
+                (from, to, gain, promotion.unwrap_or(role))
+            },
+            Move::EnPassant { from, to } =>
+                (from, to, piece_value(Role::Pawn) as i32, Role::Pawn),
+            _ => return None,
+        };
+
+        let mut occupied = board.occupied().without(from).with(to);
+        if let Move::EnPassant { .. } = *m {
+            occupied.discard(to.combine(from));
+        }
+
+        let attackers = (board.attacks_to(to, White, occupied) |
+                         board.attacks_to(to, Black, occupied)) & occupied;
+
+        let exchange = Exchange {
+            board,
+            turn: pos.turn(),
+            to,
+            occupied,
+            attackers,
+            pins: [see_pins(board, Black, to), see_pins(board, White, to)],
+        };
+
+        Some((exchange, gain, on_square))
+    }
+
+    /// Finds the least valuable piece of `side` that can recapture.
+    fn least_valuable_attacker(&self, side: Color) -> Option<(Role, Square)> {
+        let mut candidates = self.attackers & self.board.by_color(side);
+        let (pinned, pinners) = self.pins[side as usize];
+        if (pinners & self.occupied).without(self.to).any() {
+            candidates &= !pinned;
+        }
+
+        ROLES.iter()
+             .map(|&role| (role, candidates & self.board.by_role(role)))
+             .find(|&(_, bb)| bb.any())
+             .map(|(role, bb)| (role, bb.first().expect("non-empty")))
+    }
+
+    /// Tests if `side` still attacks the target square, not counting the
+    /// piece on `except`.
+    fn is_defended(&self, side: Color, except: Square) -> bool {
+        (self.attackers & self.board.by_color(side) & self.occupied.without(except)).any()
+    }
+
+    /// Removes the piece on `sq` after it recaptured, revealing x-ray
+    /// attackers behind it.
+    fn remove(&mut self, sq: Square) {
+        self.occupied.discard(sq);
+        self.attackers |= (attacks::rook_attacks(self.to, self.occupied) & self.board.rooks_and_queens()) |
+                          (attacks::bishop_attacks(self.to, self.occupied) & self.board.bishops_and_queens());
+        self.attackers &= self.occupied;
+    }
+}
+
+fn see<P: Setup + ?Sized>(pos: &P, m: &Move) -> i32 {
+    let (mut exchange, mut gain, mut on_square) = match Exchange::new(pos, m) {
+        Some(exchange) => exchange,
+        None => return 0,
+    };
+
+    // One entry for the move and one for each recapture. Every recapture
+    // removes another piece from the board, so there can be no more than
+    // 64 entries.
+    let mut gains = [0i32; 64];
+    gains[0] = gain;
+    let mut depth = 1;
+    let mut side = !exchange.turn;
+
+    while let Some((role, sq)) = exchange.least_valuable_attacker(side) {
+        if role == Role::King && exchange.is_defended(!side, sq) {
+            break;
+        }
+
+        gain = piece_value(on_square) as i32 - gain;
+        gains[depth] = gain;
+        depth += 1;
+        on_square = role;
+
+        exchange.remove(sq);
+        side = !side;
+    }
+
+    while depth > 1 {
+        depth -= 1;
+        gains[depth - 1] = -max(-gains[depth - 1], gains[depth]);
+    }
+
+    gains[0]
+}
+
+/// Like `see(pos, m) >= threshold`, but stops as soon as the result is
+/// known.
+fn see_ge<P: Setup + ?Sized>(pos: &P, m: &Move, threshold: i32) -> bool {
+    let (mut exchange, gain, on_square) = match Exchange::new(pos, m) {
+        Some(exchange) => exchange,
+        None => return 0 >= threshold,
+    };
+
+    // Even if the moved piece is captured for free, the balance is enough.
+    let mut swap = gain - threshold;
+    if swap < 0 {
+        return false;
+    }
+
+    // Even if the moved piece is lost, the balance is enough.
+    swap = piece_value(on_square) as i32 - swap;
+    if swap <= 0 {
+        return true;
+    }
+
+    // Invariant: ok is the result if the side to recapture stops now.
+    let mut ok = true;
+    let mut side = exchange.turn;
+
+    loop {
+        side = !side;
+
+        let (role, sq) = match exchange.least_valuable_attacker(side) {
+            Some(attacker) => attacker,
+            None => break,
+        };
+
+        ok = !ok;
+
+        if role == Role::King {
+            // The king can only recapture if the square is not defended.
+            return if exchange.is_defended(!side, sq) { !ok } else { ok };
+        }
+
+        swap = piece_value(role) as i32 - swap;
+        if swap < ok as i32 {
+            break;
         }
+
+        exchange.remove(sq);
     }
+
+    ok
+}
+
+/// Pieces of `color` pinned to their king that can not capture on `to`,
+/// and the pinning pieces.
+fn see_pins(board: &Board, color: Color, to: Square) -> (Bitboard, Bitboard) {
+    let king = match board.king_of(color) {
+        Some(king) => king,
+        None => return (Bitboard(0), Bitboard(0)),
+    };
+
+    let mut pinned = Bitboard(0);
+    let mut pinners = Bitboard(0);
+
+    for blocker in board.pinned(color) {
+        if !attacks::aligned(king, blocker, to) {
+            pinned.add(blocker);
+            pinners.extend(board.pinner(blocker));
+        }
+    }
+
+    (pinned, pinners)
 }
 
 impl Setup for Chess {
@@ -329,20 +1297,30 @@ impl Setup for Chess {
 
 impl Position for Chess {
     fn play_unchecked(&mut self, m: &Move) {
+        self.zobrist ^= zobrist::castling_key(&self.board, self.castling.castling_rights()) ^
+                        zobrist::ep_key(&self.board, self.turn, self.ep_square) ^
+                        zobrist::move_key(self.turn, m) ^
+                        zobrist::turn_key();
+
         do_move(&mut self.board, &mut self.turn, &mut self.castling,
                 &mut self.ep_square, &mut self.halfmove_clock,
                 &mut self.fullmoves, m);
+
+        self.zobrist ^= zobrist::castling_key(&self.board, self.castling.castling_rights()) ^
+                        zobrist::ep_key(&self.board, self.turn, self.ep_square);
     }
 
     fn from_setup<S: Setup>(setup: &S) -> Result<Chess, PositionError> {
-        let pos = Chess {
+        let mut pos = Chess {
             board: setup.board().clone(),
             turn: setup.turn(),
             castling: Castling::from_setup(setup).map_err(|_| PositionError::BadCastlingRights)?,
             ep_square: setup.ep_square(),
             halfmove_clock: setup.halfmove_clock(),
             fullmoves: setup.fullmoves(),
+            zobrist: 0,
         };
+        pos.rehash();
 
         validate_basic(&pos)
             .or_else(|| validate_kings(&pos))
@@ -356,25 +1334,180 @@ impl Position for Chess {
     fn legal_moves(&self, moves: &mut MoveList) {
         let king = self.board().king_of(self.turn()).expect("king in standard chess");
 
-        let has_ep = gen_en_passant(self.board(), self.turn(), self.ep_square, moves);
+        let has_ep = gen_pseudo_legal(self, king, moves);
+
+        let blockers = self.board().slider_blockers(self.them(), king);
+        if blockers.any() || has_ep {
+            moves.swap_retain(|m| is_safe(self, king, m, blockers));
+        }
+    }
+
+    fn pseudo_legal_moves(&self, moves: &mut MoveList) {
+        let king = self.board().king_of(self.turn()).expect("king in standard chess");
+        gen_pseudo_legal(self, king, moves);
+    }
+
+    fn count_legal_moves(&self) -> usize {
+        let king = self.board().king_of(self.turn()).expect("king in standard chess");
+        let pinned = self.board().slider_blockers(self.them(), king) & self.us();
+
+        let ep_moves = self.ep_square.map_or(0, |to| {
+            en_passant_candidates(self.board(), self.turn(), to)
+                .filter(|&from| is_safe(self, king, &Move::EnPassant { from, to }, pinned))
+                .count()
+        });
 
         let checkers = self.checkers();
         if checkers.is_empty() {
-            let target = !self.us();
-            gen_non_king(self, target, moves);
-            gen_safe_king(self, king, target, moves);
-            gen_castling_moves(self, king, CastlingSide::KingSide, moves);
-            gen_castling_moves(self, king, CastlingSide::QueenSide, moves);
+            let castling_moves = castling_move(self, king, CastlingSide::KingSide).into_iter()
+                .chain(castling_move(self, king, CastlingSide::QueenSide))
+                .count();
+
+            ep_moves + castling_moves +
+            count_safe_king(self, king, !self.us()) +
+            count_non_king(self, king, !self.us(), pinned)
+        } else {
+            let mut attacked = Bitboard(0);
+            for checker in checkers & self.board().sliders() {
+                attacked |= attacks::ray(checker, king) ^ checker;
+            }
+
+            ep_moves +
+            count_safe_king(self, king, !self.us() & !attacked) +
+            checkers.single_square().map_or(0, |checker| {
+                count_non_king(self, king, attacks::between(king, checker).with(checker), pinned)
+            })
+        }
+    }
+
+    fn gives_check(&self, m: &Move) -> bool {
+        let king = match self.board().king_of(!self.turn()) {
+            Some(king) => king,
+            None => return false,
+        };
+
+        let occupied = self.board().occupied();
+
+        // Squares vacated and the piece placed by the move.
+        let (vacated, to, piece) = match *m {
+            Move::Normal { role, from, to, promotion, .. } =>
+                (Bitboard::from_square(from), to, promotion.unwrap_or(role).of(self.turn())),
+            Move::EnPassant { from, to } =>
+                (Bitboard::from_square(from).with(to.combine(from)), to, self.turn().pawn()),
+            Move::Castle { king: king_from, rook } => {
+                let side = if king_from < rook { CastlingSide::KingSide } else { CastlingSide::QueenSide };
+                let occupied = (occupied ^ king_from ^ rook).with(side.king_to(self.turn())).with(side.rook_to(self.turn()));
+                return attacks::rook_attacks(side.rook_to(self.turn()), occupied).contains(king);
+            },
+            Move::Put { role, to } | Move::Exchange { role, to, .. } =>
+                (Bitboard(0), to, role.of(self.turn())),
+            Move::Null => return false,
+        };
+
+        let occupied = (occupied & !vacated).with(to);
+
+        if attacks::attacks(to, piece, occupied).contains(king) {
+            return true;
+        }
+
+        // Discovered checks by sliders that stayed in place.
+        let sliders = self.us() & !vacated & !Bitboard::from_square(to);
+        (attacks::rook_attacks(king, occupied) & sliders & self.board().rooks_and_queens()).any() ||
+        (attacks::bishop_attacks(king, occupied) & sliders & self.board().bishops_and_queens()).any()
+    }
+
+    fn check_moves(&self, moves: &mut MoveList) {
+        self.legal_moves(moves);
+        moves.swap_retain(|m| self.gives_check(m));
+    }
+
+    fn target_moves(&self, target: Bitboard, moves: &mut MoveList) {
+        let ep = self.ep_square.map_or(false, |sq| target.contains(sq));
+        gen_targeted(self, target, ep, target, moves);
+    }
+
+    fn capture_moves(&self, moves: &mut MoveList) {
+        gen_targeted(self, self.them(), true, Bitboard(0), moves);
+    }
+
+    fn quiet_moves(&self, moves: &mut MoveList) {
+        gen_targeted(self, !self.board().occupied(), false, Bitboard::all(), moves);
+    }
+
+    fn promotion_moves(&self, moves: &mut MoveList) {
+        let king = self.board().king_of(self.turn()).expect("king in standard chess");
+        let checkers = self.checkers();
+
+        if checkers.is_empty() {
+            gen_pawn_moves(self, bitboard::BACKRANKS & !self.us(), moves);
         } else {
             evasions(self, king, checkers, moves);
+            moves.swap_retain(|m| m.promotion().is_some());
         }
 
-        let blockers = slider_blockers(self.board(), self.them(), king);
-        if blockers.any() || has_ep {
+        let blockers = self.board().slider_blockers(self.them(), king);
+        if blockers.any() {
             moves.swap_retain(|m| is_safe(self, king, m, blockers));
         }
     }
 
+    fn is_legal_pseudo(&self, m: &Move) -> bool {
+        let king = self.board().king_of(self.turn()).expect("king in standard chess");
+        is_safe(self, king, m, self.board().slider_blockers(self.them(), king))
+    }
+
+    fn is_pseudo_legal(&self, m: &Move) -> bool {
+        let king = self.board().king_of(self.turn()).expect("king in standard chess");
+        let checkers = self.checkers();
+
+        let (role, from, to) = match *m {
+            Move::Normal { role, from, capture, to, promotion } => {
+                if self.board().piece_at(from) != Some(role.of(self.turn())) ||
+                   self.us().contains(to) ||
+                   capture != self.board().role_at(to) ||
+                   capture == Some(Role::King) {
+                    return false;
+                }
+
+                let promotes = role == Role::Pawn && bitboard::BACKRANKS.contains(to);
+                match promotion {
+                    Some(Role::Pawn) | Some(Role::King) => return false,
+                    Some(_) if !promotes => return false,
+                    None if promotes => return false,
+                    _ => (),
+                }
+
+                (role, from, to)
+            },
+            Move::EnPassant { from, to } => {
+                return Some(to) == self.ep_square &&
+                       self.our(Role::Pawn).contains(from) &&
+                       attacks::pawn_attacks(self.turn(), from).contains(to);
+            },
+            Move::Castle { king: k, rook } => {
+                let side = if k < rook { CastlingSide::KingSide } else { CastlingSide::QueenSide };
+                return castling_move(self, king, side).as_ref() == Some(m);
+            },
+            Move::Put { .. } | Move::Exchange { .. } => return false,
+            Move::Null => return checkers.is_empty(),
+        };
+
+        if role == Role::King {
+            return attacks::king_attacks(from).contains(to) &&
+                   self.board().attacks_to(to, !self.turn(), self.board().occupied() ^ from).is_empty();
+        }
+
+        if !reachable(self.board(), self.turn(), role, from).contains(to) {
+            return false;
+        }
+
+        match checkers.single_square() {
+            _ if checkers.is_empty() => true,
+            Some(checker) => attacks::between(king, checker).with(checker).contains(to),
+            None => false, // double check
+        }
+    }
+
     fn castling_moves(&self, side: CastlingSide, moves: &mut MoveList) {
         let king = self.board().king_of(self.turn()).expect("king in standard chess");
         gen_castling_moves(self, king, side, moves);
@@ -424,38 +1557,340 @@ impl Position for Chess {
             Some(to) == self.ep_square &&
             gen_en_passant(self.board(), self.turn(), self.ep_square, moves);
 
-        let blockers = slider_blockers(self.board(), self.them(), king);
-        if blockers.any() || has_ep {
-            moves.swap_retain(|m| is_safe(self, king, m, blockers));
+        let blockers = self.board().slider_blockers(self.them(), king);
+        if blockers.any() || has_ep {
+            moves.swap_retain(|m| is_safe(self, king, m, blockers));
+        }
+    }
+
+    fn is_insufficient_material(&self) -> bool {
+        self.has_insufficient_material(White) && self.has_insufficient_material(Black)
+    }
+
+    fn see(&self, m: &Move) -> Option<i32> {
+        Some(see(self, m))
+    }
+
+    fn see_ge(&self, m: &Move, threshold: i32) -> Option<bool> {
+        Some(see_ge(self, m, threshold))
+    }
+
+    fn is_dead_position(&self) -> bool {
+        self.is_insufficient_material() || is_blockade(self)
+    }
+
+    fn has_insufficient_material(&self, color: Color) -> bool {
+        let ours = self.board().by_color(color);
+        let theirs = self.board().by_color(!color);
+
+        if (ours & (self.board().pawns() | self.board().rooks_and_queens())).any() {
+            return false;
+        }
+
+        // A single knight can mate only if the opponent has pieces to block
+        // their own king. Queens are too mobile for that.
+        if (ours & self.board().knights()).any() {
+            return ours.count() <= 2 &&
+                   (theirs & !self.board().kings() & !self.board().queens()).is_empty();
+        }
+
+        // Bishops can mate only with the help of bishops on the other
+        // color, or if the opponent can block with knights or pawns.
+        if (ours & self.board().bishops()).any() {
+            let same_color = (self.board().bishops() & bitboard::DARK_SQUARES).is_empty() ||
+                             (self.board().bishops() & bitboard::LIGHT_SQUARES).is_empty();
+            return same_color && self.board().knights().is_empty() && self.board().pawns().is_empty();
+        }
+
+        true
+    }
+
+    fn is_variant_end(&self) -> bool { false }
+    fn variant_outcome(&self) -> Option<Outcome> { None }
+}
+
+/// Hooks to customize the rules of standard chess.
+///
+/// All hooks have default implementations, so that a house variant only
+/// needs to implement the rules it changes. See `Custom`.
+pub trait Rules {
+    /// Additional filter for moves that are legal in standard chess.
+    fn is_legal(&self, _pos: &Chess, _m: &Move) -> bool { true }
+
+    /// Side effects after a move of `color` has been played by the standard
+    /// rules.
+    fn after_move(&self, _state: &mut CustomState, _color: Color, _m: &Move) { }
+
+    /// Special winning, losing and drawing conditions.
+    fn outcome(&self, _pos: &Chess) -> Option<Outcome> { None }
+
+    /// Replaces the standard insufficient material test, unless `None`.
+    fn is_insufficient_material(&self, _pos: &Chess) -> Option<bool> { None }
+
+    /// Replaces the standard test if `color` has insufficient material to
+    /// win, unless `None`.
+    fn has_insufficient_material(&self, _pos: &Chess, _color: Color) -> Option<bool> { None }
+}
+
+/// The state of a `Custom` position that `Rules::after_move()` can change.
+///
+/// Castling rights can only be removed. Pockets are `None` unless the
+/// position was set up with pockets or the rules add them.
+pub struct CustomState<'a> {
+    pub board: &'a mut Board,
+    pub castling_rights: Bitboard,
+    pub ep_square: &'a mut Option<Square>,
+    pub pockets: &'a mut Option<Pockets>,
+}
+
+/// A chess position with custom `Rules`.
+///
+/// # Examples
+///
+/// ```
+/// use shakmaty::{Chess, Position, Rules, Custom, Move, Role};
+///
+/// #[derive(Default)]
+/// struct NoKnightMoves;
+///
+/// impl Rules for NoKnightMoves {
+///     fn is_legal(&self, _pos: &Chess, m: &Move) -> bool {
+///         match *m {
+///             Move::Normal { role: Role::Knight, .. } => false,
+///             _ => true,
+///         }
+///     }
+/// }
+///
+/// let pos = Custom::new(Chess::default(), NoKnightMoves);
+/// assert_eq!(pos.legals().len(), 16);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Custom<R> {
+    chess: Chess,
+    pockets: Option<Pockets>,
+    rules: R,
+}
+
+impl<R: Rules> Custom<R> {
+    pub fn new(chess: Chess, rules: R) -> Custom<R> {
+        Custom { chess, pockets: None, rules }
+    }
+
+    /// The position as seen by the standard rules.
+    pub fn chess(&self) -> &Chess {
+        &self.chess
+    }
+
+    pub fn rules(&self) -> &R {
+        &self.rules
+    }
+}
+
+impl<R> Setup for Custom<R> {
+    fn board(&self) -> &Board { self.chess.board() }
+    fn pockets(&self) -> Option<&Pockets> { self.pockets.as_ref() }
+    fn turn(&self) -> Color { self.chess.turn() }
+    fn castling_rights(&self) -> Bitboard { self.chess.castling_rights() }
+    fn ep_square(&self) -> Option<Square> { self.chess.ep_square() }
+    fn remaining_checks(&self) -> Option<&RemainingChecks> { None }
+    fn halfmove_clock(&self) -> u32 { self.chess.halfmove_clock() }
+    fn fullmoves(&self) -> u32 { self.chess.fullmoves() }
+}
+
+impl<R: Rules + Default> Position for Custom<R> {
+    fn play_unchecked(&mut self, m: &Move) {
+        let color = self.chess.turn;
+        self.chess.play_unchecked(m);
+
+        let board = self.chess.board.clone();
+        let ep_square = self.chess.ep_square;
+        let castling_rights = self.chess.castling.castling_rights();
+        let kept = {
+            let mut state = CustomState {
+                board: &mut self.chess.board,
+                castling_rights,
+                ep_square: &mut self.chess.ep_square,
+                pockets: &mut self.pockets,
+            };
+            self.rules.after_move(&mut state, color, m);
+            state.castling_rights
+        };
+
+        for rook in castling_rights & !kept {
+            self.chess.castling.discard_rook(rook);
+        }
+
+        // Update the key with the changes made by the rules.
+        for &color in &[White, Black] {
+            for &role in &ROLES {
+                let piece = role.of(color);
+                for sq in board.by_piece(piece) ^ self.chess.board.by_piece(piece) {
+                    self.chess.zobrist ^= zobrist::piece_key(piece, sq);
+                }
+            }
+        }
+
+        if kept != castling_rights || board.kings() != self.chess.board.kings() {
+            self.chess.zobrist ^= zobrist::castling_key(&board, castling_rights) ^
+                                  zobrist::castling_key(&self.chess.board, self.chess.castling.castling_rights());
+        }
+
+        if ep_square != self.chess.ep_square || board.pawns() != self.chess.board.pawns() {
+            self.chess.zobrist ^= zobrist::ep_key(&board, self.chess.turn, ep_square) ^
+                                  zobrist::ep_key(&self.chess.board, self.chess.turn, self.chess.ep_square);
+        }
+    }
+
+    fn from_setup<S: Setup>(setup: &S) -> Result<Custom<R>, PositionError> {
+        Chess::from_setup(setup).map(|chess| Custom {
+            chess,
+            pockets: setup.pockets().cloned(),
+            rules: R::default(),
+        })
+    }
+
+    fn castling_uncovers_rank_attack(&self, rook: Square, king_to: Square) -> bool {
+        self.chess.castling_uncovers_rank_attack(rook, king_to)
+    }
+
+    fn legal_moves(&self, moves: &mut MoveList) {
+        self.chess.legal_moves(moves);
+        moves.swap_retain(|m| self.rules.is_legal(&self.chess, m));
+    }
+
+    fn castling_moves(&self, side: CastlingSide, moves: &mut MoveList) {
+        self.chess.castling_moves(side, moves);
+        moves.swap_retain(|m| self.rules.is_legal(&self.chess, m));
+    }
+
+    fn san_candidates(&self, role: Role, to: Square, moves: &mut MoveList) {
+        self.chess.san_candidates(role, to, moves);
+        moves.swap_retain(|m| self.rules.is_legal(&self.chess, m));
+    }
+
+    fn is_legal(&self, m: &Move) -> bool {
+        self.chess.is_legal(m) && self.rules.is_legal(&self.chess, m)
+    }
+
+    fn see(&self, m: &Move) -> Option<i32> {
+        self.chess.see(m)
+    }
+
+    fn see_ge(&self, m: &Move, threshold: i32) -> Option<bool> {
+        self.chess.see_ge(m, threshold)
+    }
+
+    fn is_insufficient_material(&self) -> bool {
+        self.rules.is_insufficient_material(&self.chess)
+            .unwrap_or_else(|| self.chess.is_insufficient_material())
+    }
+
+    fn has_insufficient_material(&self, color: Color) -> bool {
+        self.rules.has_insufficient_material(&self.chess, color)
+            .unwrap_or_else(|| self.chess.has_insufficient_material(color))
+    }
+
+    fn is_variant_end(&self) -> bool {
+        self.rules.outcome(&self.chess).is_some()
+    }
+
+    fn variant_outcome(&self) -> Option<Outcome> {
+        self.rules.outcome(&self.chess)
+    }
+}
+
+/// Proves that a position with only kings, bishops and pawns is dead: all
+/// pawns are blocked for good, no king or bishop can ever attack an enemy
+/// pawn or step where a pawn could capture it, and no bishop can ever give
+/// check. Pawns then never move or leave the board, so neither side can be
+/// checkmated.
+///
+/// The analysis is conservative. Kings and bishops are assumed to pass
+/// freely through squares occupied by kings and bishops.
+fn is_blockade(pos: &Chess) -> bool {
+    let board = pos.board();
+
+    if (board.knights() | board.rooks_and_queens()).any() ||
+       board.pawns().is_empty() || pos.ep_square.is_some() || pos.checkers().any() {
+        return false;
+    }
+
+    // Squares where pieces of each color would be captured by a pawn.
+    let mut guarded = [Bitboard(0); 2];
+    for color in &[White, Black] {
+        for sq in board.pawns() & board.by_color(!*color) {
+            guarded[*color as usize].add_all(attacks::pawn_attacks(!*color, sq));
+        }
+    }
+
+    // Every pawn is blocked by a pawn in front of it and has nothing to
+    // capture.
+    for color in &[White, Black] {
+        let pawns = board.pawns() & board.by_color(*color);
+        if (pawns.relative_shift(*color, 8) & !board.pawns()).any() {
+            return false;
+        }
+
+        if (guarded[*color as usize] & board.by_color(*color)).any() {
+            return false;
+        }
+    }
+
+    let mut king_regions = [Bitboard(0); 2];
+
+    for color in &[White, Black] {
+        let their_pawns = board.pawns() & board.by_color(!*color);
+
+        let region = flood_fill(board.kings() & board.by_color(*color),
+                                !board.pawns() & !guarded[*color as usize],
+                                attacks::king_attacks);
+
+        let reach = region.into_iter().fold(Bitboard(0), |acc, sq| acc | attacks::king_attacks(sq));
+        if (reach & their_pawns).any() {
+            return false;
         }
+
+        king_regions[*color as usize] = region;
     }
 
-    fn is_insufficient_material(&self) -> bool {
-        if self.board().pawns().any() || self.board().rooks_and_queens().any() {
-            return false;
-        }
+    for color in &[White, Black] {
+        let their_pawns = board.pawns() & board.by_color(!*color);
 
-        if self.board().occupied().count() < 3 {
-            return true; // single knight or bishop
-        }
+        for bishop in board.bishops() & board.by_color(*color) {
+            let region = flood_fill(Bitboard::from_square(bishop),
+                                    !board.pawns(),
+                                    |sq| attacks::bishop_attacks(sq, board.pawns()));
 
-        if self.board().knights().any() {
-            return false; // more than a single knight
-        }
+            if (region & guarded[*color as usize]).any() {
+                return false; // could be captured by a pawn
+            }
 
-        // all bishops on the same color
-        if (self.board().bishops() & bitboard::DARK_SQUARES).is_empty() {
-            return true;
-        }
-        if (self.board().bishops() & bitboard::LIGHT_SQUARES).is_empty() {
-            return true;
-        }
+            let reach = region.into_iter().fold(Bitboard(0), |acc, sq| {
+                acc | attacks::bishop_attacks(sq, board.pawns())
+            });
 
-        false
+            if (reach & (their_pawns | king_regions[!*color as usize])).any() {
+                return false;
+            }
+        }
     }
 
-    fn is_variant_end(&self) -> bool { false }
-    fn variant_outcome(&self) -> Option<Outcome> { None }
+    true
+}
+
+fn flood_fill<F>(start: Bitboard, passable: Bitboard, step: F) -> Bitboard
+    where F: Fn(Square) -> Bitboard
+{
+    let mut region = start;
+
+    loop {
+        let next = region.into_iter().fold(region, |acc, sq| acc | (step(sq) & passable));
+        if next == region {
+            return region;
+        }
+        region = next;
+    }
 }
 
 fn do_move(board: &mut Board,
@@ -510,9 +1945,10 @@ fn do_move(board: &mut Board,
             board.remove_piece_at(from).map(|piece| board.set_piece_at(to, piece, false));
             *halfmove_clock = 0;
         },
-        Move::Put { role, to } => {
+        Move::Put { role, to } | Move::Exchange { role, to, .. } => {
             board.set_piece_at(to, Piece { color, role }, false);
         },
+        Move::Null => (),
     }
 
     if color.is_black() {
@@ -522,7 +1958,7 @@ fn do_move(board: &mut Board,
     *turn = !color;
 }
 
-fn validate_basic<P: Position>(pos: &P) -> Option<PositionError> {
+pub(crate) fn validate_basic<P: Position>(pos: &P) -> Option<PositionError> {
     if pos.board().occupied().is_empty() {
         return Some(PositionError::Empty);
     }
@@ -578,7 +2014,7 @@ fn validate_ep<P: Position>(pos: &P) -> Option<PositionError> {
     None
 }
 
-fn validate_kings<P: Position>(pos: &P) -> Option<PositionError> {
+pub(crate) fn validate_kings<P: Position>(pos: &P) -> Option<PositionError> {
     for color in &[White, Black] {
         if pos.board().king_of(*color).is_none() {
             return Some(PositionError::NoKing { color: *color })
@@ -598,6 +2034,56 @@ fn validate_kings<P: Position>(pos: &P) -> Option<PositionError> {
     None
 }
 
+/// Generates legal moves, except that pinned pieces and en passant captures
+/// may leave the king in check. Returns whether there are en passant
+/// captures.
+fn gen_pseudo_legal(pos: &Chess, king: Square, moves: &mut MoveList) -> bool {
+    let has_ep = gen_en_passant(pos.board(), pos.turn(), pos.ep_square, moves);
+
+    let checkers = pos.checkers();
+    if checkers.is_empty() {
+        let target = !pos.us();
+        gen_non_king(pos, target, moves);
+        gen_safe_king(pos, king, target, moves);
+        gen_castling_moves(pos, king, CastlingSide::KingSide, moves);
+        gen_castling_moves(pos, king, CastlingSide::QueenSide, moves);
+    } else {
+        evasions(pos, king, checkers, moves);
+    }
+
+    has_ep
+}
+
+/// Generates legal moves to `target`, en passant captures if `ep` and
+/// castling moves with rooks in `castling_rooks`.
+fn gen_targeted(pos: &Chess, target: Bitboard, ep: bool, castling_rooks: Bitboard, moves: &mut MoveList) {
+    let king = pos.board().king_of(pos.turn()).expect("king in standard chess");
+
+    let has_ep = ep && gen_en_passant(pos.board(), pos.turn(), pos.ep_square, moves);
+
+    let checkers = pos.checkers();
+    if checkers.is_empty() {
+        let target = target & !pos.us();
+        gen_non_king(pos, target, moves);
+        gen_safe_king(pos, king, target, moves);
+
+        for side in &[CastlingSide::KingSide, CastlingSide::QueenSide] {
+            if pos.castling.rook(pos.turn(), *side).map_or(false, |rook| castling_rooks.contains(rook)) {
+                gen_castling_moves(pos, king, *side, moves);
+            }
+        }
+    } else {
+        let mut evasion_moves = MoveList::new();
+        evasions(pos, king, checkers, &mut evasion_moves);
+        moves.extend(evasion_moves.drain(..).filter(|m| m.to_opt().map_or(false, |to| target.contains(to))));
+    }
+
+    let blockers = pos.board().slider_blockers(pos.them(), king);
+    if blockers.any() || has_ep {
+        moves.swap_retain(|m| is_safe(pos, king, m, blockers));
+    }
+}
+
 fn gen_non_king<P: Position>(pos: &P, target: Bitboard, moves: &mut MoveList) {
     gen_pawn_moves(pos, target, moves);
     KnightTag::gen_moves(pos, target, moves);
@@ -606,6 +2092,39 @@ fn gen_non_king<P: Position>(pos: &P, target: Bitboard, moves: &mut MoveList) {
     QueenTag::gen_moves(pos, target, moves);
 }
 
+/// Counts the moves of pieces other than the king to `target`, using the
+/// same rules as `gen_non_king()` restricted by pins.
+fn count_non_king<P: Position>(pos: &P, king: Square, target: Bitboard, pinned: Bitboard) -> usize {
+    let turn = pos.turn();
+    let occupied = pos.board().occupied();
+    let mut count = 0;
+
+    for from in pos.our(Role::Pawn) {
+        let mut dests = reachable(pos.board(), turn, Role::Pawn, from) & target;
+        if pinned.contains(from) {
+            dests &= attacks::ray(king, from);
+        }
+        count += dests.count() + 3 * (dests & bitboard::BACKRANKS).count();
+    }
+
+    for from in pos.us() & !pos.board().pawns() & !pos.board().kings() {
+        let piece = pos.board().piece_at(from).expect("piece on own square");
+        let mut dests = attacks::attacks(from, piece, occupied) & target;
+        if pinned.contains(from) {
+            dests &= attacks::ray(king, from);
+        }
+        count += dests.count();
+    }
+
+    count
+}
+
+fn count_safe_king<P: Position>(pos: &P, king: Square, target: Bitboard) -> usize {
+    (attacks::king_attacks(king) & target).filter(|&to| {
+        pos.board().attacks_to(to, !pos.turn(), pos.board().occupied()).is_empty()
+    }).count()
+}
+
 fn gen_safe_king<P: Position>(pos: &P, king: Square, target: Bitboard, moves: &mut MoveList) {
     assert!(moves.len() + 8 < moves.capacity());
 
@@ -641,26 +2160,33 @@ fn evasions<P: Position>(pos: &P, king: Square, checkers: Bitboard, moves: &mut
 }
 
 fn gen_castling_moves(pos: &Chess, king: Square, side: CastlingSide, moves: &mut MoveList) {
-    if let Some(rook) = pos.castling.rook(pos.turn(), side) {
-        let path = pos.castling.path(pos.turn(), side);
-        if (path & pos.board().occupied()).any() {
-            return;
-        }
+    if let Some(m) = castling_move(pos, king, side) {
+        moves.push(m);
+    }
+}
 
-        let king_to = side.king_to(pos.turn());
-        let king_path = attacks::between(king, king_to).with(king_to).with(king);
-        for sq in king_path {
-            if pos.king_attackers(sq, !pos.turn(), pos.board().occupied() ^ king).any() {
-                return;
-            }
-        }
+/// The legal castling move to `side`, if any.
+fn castling_move(pos: &Chess, king: Square, side: CastlingSide) -> Option<Move> {
+    let rook = pos.castling.rook(pos.turn(), side)?;
+
+    let path = pos.castling.path(pos.turn(), side);
+    if (path & pos.board().occupied()).any() {
+        return None;
+    }
 
-        if pos.castling_uncovers_rank_attack(rook, king_to) {
-            return;
+    let king_to = side.king_to(pos.turn());
+    let king_path = attacks::between(king, king_to).with(king_to).with(king);
+    for sq in king_path {
+        if pos.king_attackers(sq, !pos.turn(), pos.board().occupied() ^ king).any() {
+            return None;
         }
+    }
 
-        moves.push(Move::Castle { king, rook });
+    if pos.castling_uncovers_rank_attack(rook, king_to) {
+        return None;
     }
+
+    Some(Move::Castle { king, rook })
 }
 
 fn castling_uncovers_rank_attack<P: Position>(pos: &P, rook: Square, king_to: Square) -> bool {
@@ -845,7 +2371,7 @@ fn gen_en_passant(board: &Board, turn: Color, ep_square: Option<Square>, moves:
     let mut found = false;
 
     if let Some(to) = ep_square {
-        for from in board.pawns() & board.by_color(turn) & attacks::pawn_attacks(!turn, to) {
+        for from in en_passant_candidates(board, turn, to) {
             moves.push(Move::EnPassant { from, to });
             found = true;
         }
@@ -854,21 +2380,9 @@ fn gen_en_passant(board: &Board, turn: Color, ep_square: Option<Square>, moves:
     found
 }
 
-fn slider_blockers(board: &Board, enemy: Bitboard, king: Square) -> Bitboard {
-    let snipers = (attacks::rook_attacks(king, Bitboard(0)) & board.rooks_and_queens()) |
-                  (attacks::bishop_attacks(king, Bitboard(0)) & board.bishops_and_queens());
-
-    let mut blockers = Bitboard(0);
-
-    for sniper in snipers & enemy {
-        let b = attacks::between(king, sniper) & board.occupied();
-
-        if !b.more_than_one() {
-            blockers.add_all(b);
-        }
-    }
-
-    blockers
+/// Pawns of `turn` that can capture en passant on `to`.
+fn en_passant_candidates(board: &Board, turn: Color, to: Square) -> Bitboard {
+    board.pawns() & board.by_color(turn) & attacks::pawn_attacks(!turn, to)
 }
 
 fn is_safe<P: Position>(pos: &P, king: Square, m: &Move, blockers: Bitboard) -> bool {
@@ -893,7 +2407,7 @@ fn filter_san_candidates(role: Role, to: Square, moves: &mut MoveList) {
         Move::Normal { role: r, to: t, .. } | Move::Put { role: r, to: t } =>
             to == t && role == r,
         Move::EnPassant { to: t, .. } => role == Role::Pawn && t == to,
-        Move::Castle { .. } => false,
+        Move::Castle { .. } | Move::Exchange { .. } | Move::Null => false,
     });
 }
 
@@ -904,6 +2418,7 @@ mod tests {
     use super::*;
     use test::Bencher;
     use fen::Fen;
+    use uci::Uci;
 
     #[test]
     fn test_most_known_legals() {
@@ -971,6 +2486,143 @@ mod tests {
         })
     }
 
+    #[derive(Clone, Default)]
+    struct KingOfTheHill;
+
+    impl Rules for KingOfTheHill {
+        fn outcome(&self, pos: &Chess) -> Option<Outcome> {
+            let center = Bitboard(0x0000_0018_1800_0000);
+            if (pos.board().kings() & pos.them() & center).any() {
+                Some(Outcome::Decisive { winner: !pos.turn() })
+            } else {
+                None
+            }
+        }
+
+        fn is_insufficient_material(&self, _pos: &Chess) -> Option<bool> {
+            Some(false)
+        }
+
+        fn has_insufficient_material(&self, _pos: &Chess, _color: Color) -> Option<bool> {
+            Some(false)
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct QueensOnly;
+
+    impl Rules for QueensOnly {
+        fn is_insufficient_material(&self, pos: &Chess) -> Option<bool> {
+            Some(pos.board().queens().is_empty())
+        }
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let pos: Custom<KingOfTheHill> = "8/8/8/8/8/3K4/8/k7 w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("legal position");
+
+        assert!(!pos.is_insufficient_material());
+        assert!(!pos.has_insufficient_material(Black));
+        assert_eq!(pos.outcome(), None);
+
+        let pos = pos.play(&Move::Normal {
+            role: Role::King,
+            from: square::D3,
+            capture: None,
+            to: square::D4,
+            promotion: None,
+        }).expect("legal move");
+
+        assert!(pos.is_variant_end());
+        assert_eq!(pos.outcome(), Some(Outcome::Decisive { winner: White }));
+    }
+
+    #[test]
+    fn test_custom_insufficient_material() {
+        let pos: Custom<QueensOnly> = "8/8/8/8/8/3K4/7R/k7 w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("legal position");
+
+        // The rules only answer for both sides together.
+        assert!(pos.is_insufficient_material());
+        assert!(!pos.has_insufficient_material(White));
+        assert!(pos.has_insufficient_material(Black));
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturesToPocket;
+
+    impl Rules for CapturesToPocket {
+        fn is_legal(&self, _pos: &Chess, m: &Move) -> bool {
+            *m != Move::Null
+        }
+
+        fn after_move(&self, state: &mut CustomState, color: Color, m: &Move) {
+            if let Some(role) = m.capture() {
+                state.pockets.get_or_insert_with(Pockets::default).add(role.of(color));
+                state.castling_rights = Bitboard(0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_after_move() {
+        let pos: Custom<CapturesToPocket> = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("legal position");
+
+        assert!(!pos.is_legal(&Move::Null));
+        assert_eq!(pos.pockets(), None);
+
+        let pos = pos.play(&Move::Normal {
+            role: Role::Rook,
+            from: square::A1,
+            capture: Some(Role::Rook),
+            to: square::A8,
+            promotion: None,
+        }).expect("legal move");
+
+        assert_eq!(pos.pockets().map(|p| p.to_string()), Some("R".to_owned()));
+        assert_eq!(pos.castling_rights(), Bitboard(0));
+        assert_eq!(pos.chess.zobrist_hash(), zobrist::hash(&pos.chess));
+    }
+
+    #[derive(Clone, Default)]
+    struct Explosions;
+
+    impl Rules for Explosions {
+        fn after_move(&self, state: &mut CustomState, _color: Color, m: &Move) {
+            if let Move::Normal { capture: Some(_), to, .. } = *m {
+                for sq in attacks::king_attacks(to).with(to) & !state.board.kings() {
+                    state.board.discard_piece_at(sq);
+                }
+            }
+            *state.ep_square = None;
+        }
+    }
+
+    #[test]
+    fn test_custom_incremental_zobrist() {
+        let mut pos: Custom<Explosions> = "rnbqkbnr/ppp1pppp/8/8/3p4/2N5/PPPPPPPP/R1BQKBNR w KQkq - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("legal position");
+
+        for uci in &["e2e4", "d8d5", "e4d5", "e7e5"] {
+            let m = uci.parse::<Uci>().expect("valid uci").to_move(&pos).expect("legal move");
+            pos.play_unchecked(&m);
+
+            let mut rehashed = pos.chess.clone();
+            rehashed.rehash();
+            assert_eq!(pos.chess.zobrist_hash(), rehashed.zobrist_hash(), "{}", uci);
+        }
+    }
+
     #[test]
     fn test_pinned_san_candidate() {
         let fen = "R2r2k1/6pp/1Np2p2/1p2pP2/4p3/4K3/3r2PP/8 b - - 5 37";
@@ -992,4 +2644,468 @@ mod tests {
 
         assert_eq!(moves.len(), 1);
     }
+
+    #[test]
+    fn test_termination() {
+        let terminations = [
+            ("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3", Some(Termination::Checkmate)),
+            ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", Some(Termination::Stalemate)),
+            ("8/8/4k3/8/8/3KB3/8/8 w - - 0 1", Some(Termination::InsufficientMaterial)),
+            ("8/8/4k3/8/8/3K4/8/R7 w - - 0 1", None),
+        ];
+
+        for &(fen, termination) in &terminations {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+
+            assert_eq!(pos.termination(), termination);
+        }
+    }
+
+    fn perft_unplay(pos: &mut Chess, depth: u8) -> usize {
+        if depth < 1 {
+            return 1;
+        }
+
+        let before = ::fen::fen(pos, &::fen::FenOpts::default());
+
+        pos.legals().iter().map(|m| {
+            let undo = pos.play_undoable(m);
+            let nodes = perft_unplay(pos, depth - 1);
+            pos.unplay(m, undo);
+            assert_eq!(::fen::fen(pos, &::fen::FenOpts::default()), before);
+            nodes
+        }).sum()
+    }
+
+    #[test]
+    fn test_unplay() {
+        let mut pos: Chess = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+            .parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+
+        assert_eq!(perft_unplay(&mut pos, 3), 97862);
+
+        let mut pos: Chess = "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"
+            .parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+
+        assert_eq!(perft_unplay(&mut pos, 3), 9483);
+    }
+
+    #[test]
+    fn test_null_move() {
+        let pos: Chess = "rnbqkbnr/pppp1ppp/8/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 3"
+            .parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+
+        assert!(pos.is_legal(&Move::Null));
+        assert!(!pos.is_irreversible(&Move::Null));
+
+        let pos = pos.play(&Move::Null).expect("null move is legal");
+        assert_eq!(pos.turn(), Black);
+        assert_eq!(pos.ep_square(), None);
+        assert_eq!(pos.halfmove_clock(), 1);
+        assert_eq!(pos.fullmoves(), 3);
+        assert_eq!(pos.board(), &"rnbqkbnr/pppp1ppp/8/3Pp3/8/8/PPP1PPPP/RNBQKBNR".parse::<Board>().expect("valid board"));
+
+        let pos = pos.play(&Move::Null).expect("null move is legal");
+        assert_eq!(pos.fullmoves(), 4);
+
+        let in_check: Chess = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"
+            .parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+
+        assert!(!in_check.is_legal(&Move::Null));
+    }
+
+    #[test]
+    fn test_has_insufficient_material() {
+        let cases = [
+            ("8/8/4k3/8/8/3K4/8/8 w - - 0 1", true, true),
+            ("8/8/4k3/8/8/3KN3/8/8 w - - 0 1", true, true),
+            ("8/8/4k3/8/8/3KB3/8/8 w - - 0 1", true, true),
+            ("8/8/4k3/8/8/3KR3/8/8 b - - 0 1", false, true),
+            ("8/8/4k3/8/8/2NKN3/8/8 w - - 0 1", false, true),
+            ("8/8/4kn2/8/8/3KN3/8/8 w - - 0 1", false, false),
+            ("8/8/4kq2/8/8/3KN3/8/8 w - - 0 1", true, false),
+            ("8/8/3bk3/8/8/3KB3/8/8 w - - 0 1", true, true),
+            ("8/5b2/4k3/8/8/3KB3/8/8 w - - 0 1", false, false),
+            ("8/8/4k3/8/8/3KB3/8/7n w - - 0 1", false, false),
+            ("8/8/4k3/8/6p1/3KB3/8/8 w - - 0 1", false, false),
+        ];
+
+        for &(fen, white, black) in &cases {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+
+            assert_eq!(pos.has_insufficient_material(White), white, "{}", fen);
+            assert_eq!(pos.has_insufficient_material(Black), black, "{}", fen);
+            assert_eq!(pos.is_insufficient_material(), white && black, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_dead_position() {
+        let cases = [
+            // Locked pawn chain with kings on either side.
+            ("8/4k3/8/1p1p1p1p/pPpPpPpP/P1P1P1P1/8/4K3 w - - 0 1", true),
+            ("8/8/4k3/1p1p1p1p/pPpPpPpP/P1P1P1P1/8/4K3 w - - 0 1", true),
+            // The kings can walk around the pawns.
+            ("8/8/3k4/3p4/3P4/8/8/3K4 w - - 0 1", false),
+            ("8/4k3/8/1p1p1p1p/pPpPpPpP/P1P1P1P1/4K3/8 w - - 0 1", true),
+            // The bishop can attack a pawn.
+            ("8/4k3/8/1p1p1p1p/pPpPpPpP/P1P1P1P1/8/1K1B4 w - - 0 1", false),
+            // Bishop locked in behind its own pawns.
+            ("8/4k3/8/1p1p1p1p/pPpPpPpP/P1P1P1P1/3B4/4K3 w - - 0 1", true),
+            // Bishops on the wrong color can not get through.
+            ("4b3/4k3/8/1p1p1p1p/pPpPpPpP/P1P1P1P1/8/4K3 w - - 0 1", true),
+            ("3b4/4k3/8/1p1p1p1p/pPpPpPpP/P1P1P1P1/8/4K3 w - - 0 1", false),
+            // Pawn not blocked.
+            ("8/4k3/8/1p1p1p2/pPpPpPpP/P1P1P1P1/8/4K3 w - - 0 1", false),
+            ("8/4k3/8/8/8/8/8/4K2R w - - 0 1", false),
+        ];
+
+        for &(fen, dead) in &cases {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+
+            assert_eq!(pos.is_dead_position(), dead, "{}", fen);
+        }
+
+        let pos: Chess = "8/4k3/8/1p1p1p1p/pPpPpPpP/P1P1P1P1/8/4K3 w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+        assert_eq!(pos.outcome(), None);
+        assert_eq!(pos.termination(), None);
+        assert!(!pos.is_game_over());
+        assert_eq!(pos.outcome_with_dead_position(), Some(Outcome::Draw));
+        assert_eq!(pos.termination_with_dead_position(), Some(Termination::DeadPosition));
+    }
+
+    #[test]
+    fn test_position_eq() {
+        use std::collections::HashSet;
+
+        fn play(ucis: &[&str]) -> Chess {
+            ucis.iter().fold(Chess::default(), |pos, uci| {
+                let m = uci.parse::<Uci>().expect("valid uci").to_move(&pos).expect("legal move");
+                pos.play(&m).expect("legal move")
+            })
+        }
+
+        fn setup(fen: &str) -> Chess {
+            fen.parse::<Fen>().expect("valid fen").position().expect("valid position")
+        }
+
+        // Transpositions with different move counters.
+        assert_eq!(play(&["g1f3", "b8c6", "b1c3"]), play(&["b1c3", "b8c6", "g1f3"]));
+        assert_eq!(play(&["g1f3", "g8f6", "f3g1", "f6g8"]), Chess::default());
+
+        // En passant squares only count if the capture is legal.
+        assert_eq!(play(&["e2e4"]), setup("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"));
+        assert_ne!(play(&["e2e4", "a7a6", "e4e5", "d7d5"]),
+                   setup("rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3"));
+
+        // Lost castling rights.
+        assert_ne!(play(&["e2e4", "e7e5", "e1e2", "e8e7", "e2e1", "e7e8"]),
+                   play(&["e2e4", "e7e5"]));
+
+        let mut set = HashSet::new();
+        set.insert(play(&["g1f3", "b8c6", "b1c3"]));
+        assert!(set.contains(&play(&["b1c3", "b8c6", "g1f3"])));
+        assert!(!set.contains(&Chess::default()));
+    }
+
+    #[test]
+    fn test_pseudo_legal() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "8/8/8/K1pP3r/8/8/8/7k w - c6 0 2",
+            "4k3/8/8/8/8/8/3p4/r3K2R w K - 0 1",
+            "4k3/8/8/1b6/8/8/8/R3K2R w KQ - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "4k3/4r3/8/8/8/8/2Q1B3/4K3 w - - 0 1",
+        ];
+
+        let positions: Vec<Chess> = fens.iter().map(|fen| {
+            fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position")
+        }).collect();
+
+        let mut candidates = Vec::new();
+        for pos in &positions {
+            let mut moves = MoveList::new();
+            pos.pseudo_legal_moves(&mut moves);
+            candidates.extend(moves.iter().cloned());
+        }
+
+        for pos in &positions {
+            let mut pseudo = MoveList::new();
+            pos.pseudo_legal_moves(&mut pseudo);
+
+            let mut legals = pseudo.clone();
+            legals.retain(|m| pos.is_legal_pseudo(m));
+            assert_eq!(legals.len(), pos.legals().len());
+            assert!(legals.iter().all(|m| pos.is_legal(m)));
+
+            for m in &candidates {
+                assert_eq!(pos.is_pseudo_legal(m), pseudo.contains(m), "{} in {:?}", m, pos);
+            }
+        }
+    }
+
+    #[test]
+    fn test_move_gen() {
+        fn stage(pos: &Chess, m: &Move) -> u32 {
+            match (m.capture().is_some(), m.promotion().is_some()) {
+                (true, _) if is_winning_capture(pos, m) => 0,
+                (false, true) => 1,
+                (false, false) => 2,
+                (true, _) => 3,
+            }
+        }
+
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "8/8/8/K1pP3r/8/8/8/7k w - c6 0 2",
+        ];
+
+        for fen in &fens {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+
+            let legals = pos.legals();
+            let unhashed: Vec<Move> = pos.move_gen(None).collect();
+            assert_eq!(unhashed.len(), legals.len(), "{}", fen);
+            assert!(unhashed.iter().all(|m| legals.contains(m)), "{}", fen);
+
+            // Winning captures, promotions, quiet moves, losing captures.
+            if pos.checkers().is_empty() {
+                let stages: Vec<u32> = unhashed.iter().map(|m| stage(&pos, m)).collect();
+                assert!(stages.windows(2).all(|w| w[0] <= w[1]), "{}", fen);
+            }
+
+            // The hash move comes first and is not repeated. The other moves
+            // keep their order.
+            for hash_move in legals.iter() {
+                let staged: Vec<Move> = pos.move_gen(Some(hash_move.clone())).collect();
+                assert_eq!(staged[0], *hash_move);
+                let rest: Vec<Move> = unhashed.iter().filter(|m| *m != hash_move).cloned().collect();
+                assert_eq!(&staged[1..], &rest[..], "{}", fen);
+            }
+        }
+
+        let pos: Chess = "4k3/8/8/q2p4/1p2P3/2n5/8/R1R1K3 w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+
+        let blocked = Move::Normal { role: Role::Rook, from: square::A1, capture: None, to: square::A8, promotion: None };
+        let staged: Vec<String> = pos.move_gen(Some(blocked)).map(|m| m.to_string()).collect();
+        assert_eq!(&staged[..2], &["Ra1xa5", "e4xd5"]);
+        assert_eq!(staged.last().map(|m| &m[..]), Some("Rc1xc3"));
+        assert_eq!(staged.len(), pos.legals().len());
+    }
+
+    #[test]
+    fn test_targeted_moves() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "8/8/8/K1pP3r/8/8/8/7k w - c6 0 2",
+            "r1bqkbnr/pppp1ppp/2n5/3Pp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 3",
+            "4k3/1P6/8/8/8/8/8/R3K3 b Q - 0 1",
+        ];
+
+        fn sorted(moves: &MoveList) -> Vec<String> {
+            let mut moves: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+            moves.sort();
+            moves
+        }
+
+        for fen in &fens {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+
+            let legals = pos.legals();
+            let filtered = |f: &Fn(&Move) -> bool| {
+                let mut moves = legals.clone();
+                moves.retain(|m| f(m));
+                sorted(&moves)
+            };
+
+            let mut moves = MoveList::new();
+            pos.capture_moves(&mut moves);
+            assert_eq!(sorted(&moves), filtered(&|m| m.capture().is_some()), "{}", fen);
+
+            moves.clear();
+            pos.quiet_moves(&mut moves);
+            assert_eq!(sorted(&moves), filtered(&|m| m.capture().is_none()), "{}", fen);
+
+            moves.clear();
+            pos.promotion_moves(&mut moves);
+            assert_eq!(sorted(&moves), filtered(&|m| m.promotion().is_some()), "{}", fen);
+
+            for target in &[Bitboard::rank(2), Bitboard::file(4) | Bitboard::rank(0), pos.board().occupied()] {
+                moves.clear();
+                pos.target_moves(*target, &mut moves);
+                assert_eq!(sorted(&moves), filtered(&|m| m.to_opt().map_or(false, |to| target.contains(to))), "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gives_check() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+            "8/8/8/8/k2pP2Q/8/8/3K4 b - e3 0 1",
+            "8/8/8/1k6/3Pp3/8/8/4KQ2 b - d3 0 1",
+            "3k4/8/8/3B4/8/3R4/8/4K3 w - - 0 1",
+            "r3k3/8/8/8/8/8/8/4K3 b q - 0 1",
+        ];
+
+        for fen in &fens {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+
+            let mut checks = MoveList::new();
+            pos.check_moves(&mut checks);
+
+            for m in pos.legals().iter() {
+                let gives_check = pos.clone().play(m).expect("legal move").checkers().any();
+                assert_eq!(pos.gives_check(m), gives_check, "{} in {}", m, fen);
+                assert_eq!(checks.contains(m), gives_check, "{} in {}", m, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_see() {
+        fn see(fen: &str, uci: &str) -> i32 {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+            let m = uci.parse::<Uci>()
+                .expect("valid uci")
+                .to_move(&pos)
+                .expect("legal move");
+            let value = pos.see(&m).expect("supported");
+            for threshold in -12..13 {
+                assert_eq!(pos.see_ge(&m, threshold), Some(value >= threshold));
+            }
+            value
+        }
+
+        // Undefended pawn.
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - -", "e1e5"), 1);
+        // Pawn defended by a knight, with x-ray attackers behind.
+        assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - -", "d3e5"), -2);
+        // The queen behind the rook recaptures.
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/3R4/3QK3 w - -", "d2d5"), 1);
+        // Quiet move to an attacked square.
+        assert_eq!(see("4k3/8/8/3p4/8/2N5/8/4K3 w - -", "c3e4"), -3);
+        // The defending knight is pinned to its king.
+        assert_eq!(see("4k3/4n3/8/3p4/8/2N5/8/6K1 w - -", "c3d5"), -2);
+        assert_eq!(see("4k3/4n3/8/3p4/8/2N5/8/4R1K1 w - -", "c3d5"), 1);
+        // En passant.
+        let pos: Chess = "4k3/8/8/3pP3/8/8/8/4K3 w - d6".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+        assert_eq!(pos.see(&Move::EnPassant { from: square::E5, to: square::D6 }), Some(1));
+    }
+
+    #[test]
+    fn test_illegal_move_reason() {
+        fn reason(fen: &str, uci: &str) -> IllegalMoveReason {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+            uci.parse::<Uci>()
+                .expect("valid uci")
+                .to_move(&pos)
+                .expect_err("illegal move")
+                .reason()
+        }
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(reason(start, "e3e4"), IllegalMoveReason::NoPiece);
+        assert_eq!(reason(start, "e7e5"), IllegalMoveReason::WrongColor);
+        assert_eq!(reason(start, "g1g3"), IllegalMoveReason::InvalidMovement);
+        assert_eq!(reason(start, "e2e4q"), IllegalMoveReason::PromotionNotAllowed);
+        assert_eq!(reason(start, "g1f3q"), IllegalMoveReason::PromotionNotAllowed);
+
+        assert_eq!(reason("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1", "e2c3"), IllegalMoveReason::Pinned);
+        assert_eq!(reason("4k3/4r3/8/8/8/8/8/3K4 w - - 0 1", "d1e1"), IllegalMoveReason::KingInCheck);
+        assert_eq!(reason("4k3/4r3/8/8/8/8/8/4K1N1 w - - 0 1", "g1f3"), IllegalMoveReason::KingInCheck);
+        assert_eq!(reason("4k3/8/8/8/8/8/5r2/4K2R w K - 0 1", "e1g1"), IllegalMoveReason::CastlingThroughCheck);
+        assert_eq!(reason("4k3/8/8/8/8/8/8/4K2R w - - 0 1", "e1g1"), IllegalMoveReason::NoCastlingRights);
+        assert_eq!(reason("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8"), IllegalMoveReason::PromotionMissing);
+        assert_eq!(reason("4k3/4r3/8/8/8/8/8/4K3 w - - 0 1", "0000"), IllegalMoveReason::KingInCheck);
+        assert_eq!(reason("4k3/4r3/8/8/8/8/8/4K3 w - - 0 1", "e1e2"), IllegalMoveReason::KingInCheck);
+
+        let pos: Custom<CapturesToPocket> = start.parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+        assert_eq!(pos.validate(&Move::Null).expect_err("illegal move").reason(), IllegalMoveReason::VariantRule);
+    }
+
+    #[test]
+    fn test_count_legal_moves() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "8/8/8/8/k2pP2Q/8/8/3K4 b - e3 0 1",
+            "4k3/4r3/8/8/1b6/8/3PN3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/2n5/4r3/4K3 w - - 0 1",
+        ];
+
+        for fen in &fens {
+            let pos: Chess = fen.parse::<Fen>()
+                .expect("valid fen")
+                .position()
+                .expect("valid position");
+
+            assert_eq!(pos.count_legal_moves(), pos.legals().len(), "{}", fen);
+        }
+    }
 }
@@ -0,0 +1,202 @@
+// This file is part of the shakmaty library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Premoves, queued by the side not to move while the opponent is thinking.
+//!
+//! Premove candidates depend only on the pieces of the side not to move.
+//! Pins, checks and enemy pieces are ignored, because the reply of the
+//! opponent may change all of them.
+//!
+//! # Examples
+//!
+//! ```
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! use shakmaty::{Chess, Position};
+//! use shakmaty::uci::Uci;
+//! use shakmaty::premove;
+//!
+//! let pos = Chess::default();
+//!
+//! // Black queues a premove while White is thinking.
+//! let uci: Uci = "d7d5".parse()?;
+//! assert!(premove::premoves(&pos).contains(&uci));
+//!
+//! // After 1. e4 the premove is legal.
+//! let e4 = "e2e4".parse::<Uci>()?.to_move(&pos)?;
+//! let pos = pos.play(&e4)?;
+//! assert!(premove::is_legal_premove(&pos, &uci));
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use square::Square;
+use types::{Color, Role, Piece};
+use bitboard::Bitboard;
+use setup::{Setup, CastlingSide};
+use position::Position;
+use uci::Uci;
+use attacks;
+
+const PROMOTION_ROLES: [Role; 4] = [Role::Queen, Role::Rook, Role::Bishop, Role::Knight];
+
+/// Gets the premove destinations of the piece on `from`, if it belongs to
+/// the side not to move.
+///
+/// Only pieces of the side not to move block sliders and pawn pushes.
+/// Castling is included both as a king move to the rook and, for kings
+/// on their standard starting square, as a king move two squares towards
+/// the rook.
+pub fn premove_dests<S: Setup>(setup: &S, from: Square) -> Bitboard {
+    let color = !setup.turn();
+    let board = setup.board();
+    let ours = board.by_color(color);
+
+    let Piece { role, .. } = match board.piece_at(from) {
+        Some(piece) if piece.color == color => piece,
+        _ => return Bitboard(0),
+    };
+
+    match role {
+        Role::Pawn => {
+            let mut dests = attacks::pawn_attacks(color, from);
+            let delta = color.fold(8, -8);
+            if let Some(single) = from.offset(delta) {
+                if !ours.contains(single) {
+                    dests.add(single);
+                    if from.rank() == color.fold(1, 6) {
+                        dests.extend(single.offset(delta));
+                    }
+                }
+            }
+            dests & !ours
+        },
+        Role::King => {
+            let mut dests = attacks::king_attacks(from) & !ours;
+            for rook in setup.castling_rights() & board.rooks() & ours {
+                if !castling_path_clear(setup, color, from, rook) {
+                    continue;
+                }
+                dests.add(rook);
+                let side = if from < rook { CastlingSide::KingSide } else { CastlingSide::QueenSide };
+                if from.distance(side.king_to(color)) == 2 {
+                    dests.add(side.king_to(color));
+                }
+            }
+            dests
+        },
+        _ => attacks::attacks(from, role.of(color), ours) & !ours,
+    }
+}
+
+fn castling_path_clear<S: Setup>(setup: &S, color: Color, king: Square, rook: Square) -> bool {
+    if king.rank() != color.fold(0, 7) || rook.rank() != king.rank() {
+        return false;
+    }
+
+    let side = if king < rook { CastlingSide::KingSide } else { CastlingSide::QueenSide };
+
+    let king_to = side.king_to(color);
+    let rook_to = side.rook_to(color);
+    let path = attacks::between(king, king_to).with(king_to) |
+               attacks::between(rook, rook_to).with(rook_to) |
+               attacks::between(king, rook);
+
+    (path & setup.board().by_color(color)).without(king).without(rook).is_empty()
+}
+
+/// Collects premove candidates for the side not to move. Pawn moves to the
+/// backrank are included with every possible promotion.
+pub fn premoves<S: Setup>(setup: &S) -> Vec<Uci> {
+    let color = !setup.turn();
+    let board = setup.board();
+    let mut moves = Vec::new();
+
+    for from in board.by_color(color) {
+        let pawn = board.pawns().contains(from);
+        for to in premove_dests(setup, from) {
+            if pawn && to.rank() == color.fold(7, 0) {
+                for &promotion in &PROMOTION_ROLES {
+                    moves.push(Uci::Normal { from, to, promotion: Some(promotion) });
+                }
+            } else {
+                moves.push(Uci::Normal { from, to, promotion: None });
+            }
+        }
+    }
+
+    moves
+}
+
+/// Tests if a queued premove is legal, once the opponent has replied and
+/// it is the turn of the side that queued it.
+pub fn is_legal_premove<P: Position>(pos: &P, uci: &Uci) -> bool {
+    uci.to_move(pos).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use square;
+    use fen::Fen;
+    use position::Chess;
+    use movelist::MoveList;
+
+    #[test]
+    fn test_premove_dests() {
+        // White to move, premoves for black ignore the white knight in the
+        // castling path and the white rook on a1.
+        let pos: Chess = "rN2k2r/8/8/8/8/8/8/R3K3 w kq - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("valid position");
+
+        let king = premove_dests(&pos, square::E8);
+        assert!(king.contains(square::G8));
+        assert!(king.contains(square::H8));
+        assert!(king.contains(square::C8));
+        assert!(king.contains(square::A8));
+
+        assert!(premove_dests(&pos, square::A8).contains(square::A1));
+        assert!(premove_dests(&pos, square::E1).is_empty());
+    }
+
+    #[test]
+    fn test_premoves_cover_legal_replies() {
+        let pos = Chess::default();
+        let premoves = premoves(&pos);
+
+        for reply in pos.legals().iter() {
+            let after = pos.clone().play(reply).expect("legal move");
+            let mut legals = MoveList::new();
+            after.legal_moves(&mut legals);
+            for m in &legals {
+                assert!(premoves.contains(&Uci::from(m)), "{} after {}", m, reply);
+            }
+            for uci in &premoves {
+                if legals.iter().any(|m| Uci::from(m) == *uci) {
+                    assert!(is_legal_premove(&after, uci));
+                }
+            }
+        }
+    }
+}
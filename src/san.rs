@@ -174,6 +174,7 @@ pub enum San {
     },
     Castle(CastlingSide),
     Put { role: Role, to: Square },
+    Exchange { hostage: Role, role: Role, to: Square },
     Null,
 }
 
@@ -216,6 +217,12 @@ impl San {
                 role: Role::from_char(san[0] as char).ok_or(())?,
                 to: Square::from_bytes(&san[2..]).map_err(|_| ())?
             })
+        } else if san.len() == 6 && san[1] == b'&' && san[3] == b'@' {
+            Ok(San::Exchange {
+                hostage: Role::from_char(san[0] as char).ok_or(())?,
+                role: Role::from_char(san[2] as char).ok_or(())?,
+                to: Square::from_bytes(&san[4..]).map_err(|_| ())?
+            })
         } else {
             let mut chars = san.iter();
 
@@ -303,7 +310,21 @@ impl San {
                     _ => false,
                 });
             },
-            San::Null => return Err(SanError::IllegalSan),
+            San::Exchange { hostage, role, to } => {
+                let m = Move::Exchange { hostage, role, to };
+                return if pos.is_legal(&m) {
+                    Ok(m)
+                } else {
+                    Err(SanError::IllegalSan)
+                };
+            },
+            San::Null => {
+                return if pos.is_legal(&Move::Null) {
+                    Ok(Move::Null)
+                } else {
+                    Err(SanError::IllegalSan)
+                };
+            },
         }
 
         legals.split_first().map_or(Err(SanError::IllegalSan), |(m, others)| {
@@ -351,6 +372,9 @@ impl fmt::Display for San {
             San::Castle(CastlingSide::QueenSide) => write!(f, "O-O-O"),
             San::Put { role: Role::Pawn, to } => write!(f, "@{}", to),
             San::Put { role, to } => write!(f, "{}@{}", role.char().to_ascii_uppercase(), to),
+            San::Exchange { hostage, role, to } =>
+                write!(f, "{}&{}@{}", hostage.char().to_ascii_uppercase(),
+                                      role.char().to_ascii_uppercase(), to),
             San::Null => write!(f, "--"),
         }
     }
@@ -450,6 +474,8 @@ pub fn san<P: Position>(pos: &P, m: &Move) -> San {
         Move::Castle { rook, king } if rook.file() < king.file() => San::Castle(CastlingSide::QueenSide),
         Move::Castle { .. } => San::Castle(CastlingSide::KingSide),
         Move::Put { role, to } => San::Put { role, to },
+        Move::Exchange { hostage, role, to } => San::Exchange { hostage, role, to },
+        Move::Null => San::Null,
     }
 }
 
@@ -458,6 +484,7 @@ mod tests {
     use super::*;
     use test::Bencher;
     use square;
+    use position::Chess;
 
     #[test]
     fn test_read_write() {
@@ -465,13 +492,22 @@ mod tests {
                      "hxg7", "bxc1", "axe4", "bxc1+", "bxa8=R+",
                      "Nf3", "Ba5", "Qh8", "Kh1", "Qh1=K", "Ba5", "Bba5",
                      "N2c4", "Red3", "Qh1=K", "d1=N", "@e4#",
-                     "K@b3", "Ba5", "Bba5",
+                     "K@b3", "Ba5", "Bba5", "R&N@e4", "P&P@b6+",
                      "Ra1a8", "--", "O-O", "O-O-O+"] {
             let result = san.parse::<SanPlus>().expect("valid san").to_string();
             assert_eq!(*san, result, "read {} write {}", san, result);
         }
     }
 
+    #[test]
+    fn test_null_move() {
+        let pos = Chess::default();
+        let m = San::Null.to_move(&pos).expect("null move is legal");
+        assert_eq!(m, Move::Null);
+        assert_eq!(san(&pos, &m), San::Null);
+        assert_eq!(san_plus(pos, &m).to_string(), "--");
+    }
+
     #[bench]
     fn bench_parse_san_move_complicated(b: &mut Bencher) {
         b.iter(|| {
@@ -34,6 +34,12 @@ pub trait Setup {
     fn halfmove_clock(&self) -> u32;
     fn fullmoves(&self) -> u32;
 
+    /// Pieces held hostage by each side, as in Hostage chess. Defaults to
+    /// `None`.
+    fn prisons(&self) -> Option<&Pockets> {
+        None
+    }
+
     fn us(&self) -> Bitboard {
         self.board().by_color(self.turn())
     }
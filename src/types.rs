@@ -25,7 +25,7 @@ pub use self::Color::{Black, White};
 pub use self::Role::{Pawn, Knight, Bishop, Rook, Queen, King};
 
 /// `White` or `Black`.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum Color {
     Black = 0,
     White = 1,
@@ -84,7 +84,7 @@ impl ops::Not for Color {
 }
 
 /// Piece types: `Pawn`, `Knight`, `Bishop`, `Rook`, `Queen`, `King`.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum Role {
     Pawn = 0,
     Knight = 1,
@@ -127,7 +127,7 @@ impl Role {
 pub const ROLES: [Role; 6] = [Pawn, Knight, Bishop, Rook, Queen, King];
 
 /// A piece with `Color` and `Role`.
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub struct Piece {
     pub color: Color,
     pub role: Role,
@@ -146,7 +146,7 @@ impl Piece {
 }
 
 /// Information about a move.
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum Move {
     Normal {
         role: Role,
@@ -158,24 +158,56 @@ pub enum Move {
     EnPassant { from: Square, to: Square },
     Castle { king: Square, rook: Square },
     Put { role: Role, to: Square },
+    /// Releases the `hostage` from the prison of the moving side to the
+    /// airfield of the opponent, in exchange for rescuing a piece of type
+    /// `role` and dropping it on `to` (in Hostage chess).
+    Exchange { hostage: Role, role: Role, to: Square },
+    /// Passes the turn to the opponent without moving a piece. Null moves
+    /// have no target square, so `to()` panics for them.
+    Null,
 }
 
 impl Move {
-    /// Gets the origin square or `None` for drops.
+    /// Gets the origin square or `None` for drops and null moves.
     pub fn from(&self) -> Option<Square> {
         match *self {
             Move::Normal { from, .. } | Move::EnPassant { from, .. } => Some(from),
             Move::Castle { king, .. } => Some(king),
-            Move::Put { .. } => None,
+            Move::Put { .. } | Move::Exchange { .. } | Move::Null => None,
         }
     }
 
     /// Gets the target square. For castling moves this is the corresponding
     /// rook square.
+    ///
+    /// # Panics
+    ///
+    /// Panics for null moves, which have no target square. Use `to_opt()`
+    /// if the move may be a null move, for example any move accepted by
+    /// `Position::is_legal()`.
     pub fn to(&self) -> Square {
+        self.to_opt().expect("null move has no target square")
+    }
+
+    /// Gets the target square or `None` for null moves. For castling moves
+    /// this is the corresponding rook square.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shakmaty::{Move, Role};
+    /// use shakmaty::square;
+    ///
+    /// let m = Move::Put { role: Role::Knight, to: square::F3 };
+    /// assert_eq!(m.to_opt(), Some(square::F3));
+    /// assert_eq!(Move::Null.to_opt(), None);
+    /// ```
+    pub fn to_opt(&self) -> Option<Square> {
         match *self {
-            Move::Normal { to, .. } | Move::EnPassant { to, .. } | Move::Put { to, .. } => to,
-            Move::Castle { rook, .. } => rook,
+            Move::Normal { to, .. } | Move::EnPassant { to, .. } |
+                Move::Put { to, .. } | Move::Exchange { to, .. } => Some(to),
+            Move::Castle { rook, .. } => Some(rook),
+            Move::Null => None,
         }
     }
 
@@ -226,12 +258,17 @@ impl fmt::Display for Move {
             Move::Put { role, to } => {
                 write!(f, "{}@{}", role.char().to_ascii_uppercase(), to)
             },
+            Move::Exchange { hostage, role, to } => {
+                write!(f, "{}&{}@{}", hostage.char().to_ascii_uppercase(),
+                                      role.char().to_ascii_uppercase(), to)
+            },
+            Move::Null => write!(f, "--"),
         }
     }
 }
 
 /// A players Crazyhouse pocket.
-#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
 pub struct Pocket {
     pub pawns: u8,
     pub knights: u8,
@@ -275,7 +312,7 @@ impl Pocket {
 }
 
 /// Pockets to hold captured pieces for both sides (in Crazyhouse).
-#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
 pub struct Pockets {
     pub white: Pocket,
     pub black: Pocket,
@@ -323,9 +360,50 @@ impl fmt::Display for Pockets {
     }
 }
 
+/// Prisons and airfields of both sides (in Hostage chess).
+///
+/// Captured pieces go to the prison of the capturing side, so
+/// `prisons.white` holds black pieces and vice versa. Released pieces go to
+/// the airfield of their own color, from where they can be dropped.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct Hostages {
+    pub prisons: Pockets,
+    pub airfields: Pockets,
+}
+
+impl Hostages {
+    /// Puts a piece captured by `capturer` into prison.
+    pub fn capture(&mut self, capturer: Color, role: Role) {
+        let count = self.prisons.by_color_mut(capturer).by_role_mut(role);
+        *count = count.saturating_add(1);
+    }
+
+    /// Releases `hostage` to the airfield of the opponent of `color` and
+    /// rescues a piece of type `role` from the opponents prison.
+    pub fn exchange(&mut self, color: Color, hostage: Role, role: Role) {
+        let released = self.prisons.by_color_mut(color).by_role_mut(hostage);
+        *released = released.saturating_sub(1);
+        self.airfields.add(hostage.of(!color));
+        let rescued = self.prisons.by_color_mut(!color).by_role_mut(role);
+        *rescued = rescued.saturating_sub(1);
+    }
+
+    /// Swaps a promoting pawn of `color` with a piece of type `role` from the
+    /// opponents prison.
+    pub fn promote(&mut self, color: Color, role: Role) {
+        let prison = self.prisons.by_color_mut(!color);
+        *prison.by_role_mut(role) = prison.by_role(role).saturating_sub(1);
+        *prison.by_role_mut(Pawn) = prison.by_role(Pawn).saturating_add(1);
+    }
+
+    pub fn count(&self) -> u8 {
+        self.prisons.count().saturating_add(self.airfields.count())
+    }
+}
+
 /// The number of checks the respective side needs to give in order to in
 /// (in a game of Three-Check).
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub struct RemainingChecks {
     pub white: u8,
     pub black: u8,
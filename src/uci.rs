@@ -97,7 +97,7 @@ use std::error::Error;
 use square;
 use square::Square;
 use types::{Role, Move};
-use position::{Position, IllegalMove};
+use position::{Position, IllegalMove, IllegalMoveReason};
 
 /// Error when parsing an invalid UCI.
 pub struct InvalidUci {
@@ -137,6 +137,7 @@ pub enum Uci {
         promotion: Option<Role>,
     },
     Put { role: Role, to: Square },
+    Exchange { hostage: Role, role: Role, to: Square },
     Null,
 }
 
@@ -157,6 +158,9 @@ impl fmt::Display for Uci {
                 write!(f, "{}{}{}", from, to, promotion.char()),
             Uci::Put { to, role } =>
                 write!(f, "{}@{}", role.char().to_ascii_uppercase(), to),
+            Uci::Exchange { hostage, role, to } =>
+                write!(f, "{}&{}@{}", hostage.char().to_ascii_uppercase(),
+                                      role.char().to_ascii_uppercase(), to),
             Uci::Null =>
                 write!(f, "0000")
         }
@@ -174,6 +178,10 @@ impl<'a> From<&'a Move> for Uci {
                 Uci::Normal { from: king, to: rook, promotion: None },  // Chess960-style
             Move::Put { role, to } =>
                 Uci::Put { role, to },
+            Move::Exchange { hostage, role, to } =>
+                Uci::Exchange { hostage, role, to },
+            Move::Null =>
+                Uci::Null,
         }
     }
 }
@@ -187,7 +195,7 @@ impl From<Move> for Uci {
 impl Uci {
     /// Parses a move in UCI notation.
     pub fn from_bytes(uci: &[u8]) -> Result<Uci, InvalidUci> {
-        if uci.len() != 4 && uci.len() != 5 {
+        if uci.len() != 4 && uci.len() != 5 && uci.len() != 6 {
             return Err(InvalidUci { _priv: () });
         }
 
@@ -195,6 +203,18 @@ impl Uci {
             return Ok(Uci::Null);
         }
 
+        if uci.len() == 6 {
+            if uci[1] != b'&' || uci[3] != b'@' {
+                return Err(InvalidUci { _priv: () });
+            }
+
+            return Ok(Uci::Exchange {
+                hostage: Role::from_char(uci[0] as char).ok_or(())?,
+                role: Role::from_char(uci[2] as char).ok_or(())?,
+                to: Square::from_bytes(&uci[4..6]).map_err(|_| ())?,
+            });
+        }
+
         let to = Square::from_bytes(&uci[2..4]).map_err(|_| ())?;
 
         if uci[1] == b'@' {
@@ -222,10 +242,10 @@ impl Uci {
     pub fn to_move<P: Position>(&self, pos: &P) -> Result<Move, IllegalMove> {
         let candidate = match *self {
             Uci::Normal { from, to, promotion } => {
-                let role = pos.board().role_at(from).ok_or(IllegalMove {})?;
+                let role = pos.board().role_at(from).ok_or(IllegalMoveReason::NoPiece)?;
 
                 if promotion.is_some() && role != Role::Pawn {
-                    return Err(IllegalMove {})
+                    return Err(IllegalMoveReason::PromotionNotAllowed.into())
                 }
 
                 if role == Role::King && pos.castling_rights().contains(to) {
@@ -244,13 +264,10 @@ impl Uci {
                 }
             },
             Uci::Put { role, to } => Move::Put { role, to },
-            Uci::Null => return Err(IllegalMove {})
+            Uci::Exchange { hostage, role, to } => Move::Exchange { hostage, role, to },
+            Uci::Null => Move::Null,
         };
 
-        if pos.is_legal(&candidate) {
-            Ok(candidate)
-        } else {
-            Err(IllegalMove {})
-        }
+        pos.validate(&candidate).map(|_| candidate)
     }
 }
@@ -0,0 +1,1203 @@
+// This file is part of the shakmaty library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Chess variants.
+//!
+//! # Examples
+//!
+//! Shatranj, the historical predecessor of chess:
+//!
+//! ```
+//! use shakmaty::{Position, MoveList};
+//! use shakmaty::variants::Shatranj;
+//!
+//! let pos = Shatranj::default();
+//! assert_eq!(pos.legals().len(), 16);
+//! ```
+//!
+//! Hostage chess, where captured pieces can be exchanged and dropped back:
+//!
+//! ```
+//! use shakmaty::{Position, MoveList};
+//! use shakmaty::variants::Hostage;
+//!
+//! let pos = Hostage::default();
+//! assert_eq!(pos.legals().len(), 20);
+//! assert_eq!(pos.hostages().count(), 0);
+//! ```
+//!
+//! Prototyping variants with fairy pieces:
+//!
+//! ```
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! use std::sync::Arc;
+//! use shakmaty::{Color, Role, Position, MoveList};
+//! use shakmaty::variants::{Fairy, PieceSet};
+//!
+//! // Replace the bishops with wazir-ferz combinations.
+//! let mut pieces = PieceSet::default();
+//! pieces.set(Role::Bishop, 'a', "WF".parse()?)?;
+//!
+//! let board = pieces.parse_board("rnaqkanr/pppppppp/8/8/8/8/PPPPPPPP/RNAQKANR")?;
+//! let pos = Fairy::new(Arc::new(pieces), board, Color::White)?;
+//! assert_eq!(pos.legals().len(), 12);
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use std::fmt;
+use std::error::Error;
+use std::sync::Arc;
+
+use attacks;
+use betza::Betza;
+use fen;
+use fen::{FenOpts, FenError};
+use board::Board;
+use bitboard;
+use bitboard::Bitboard;
+use square::Square;
+use types::{Color, White, Black, Role, Piece, Move, Pockets, Hostages, RemainingChecks, ROLES};
+use setup::{Setup, CastlingSide};
+use position::{Position, Outcome, PositionError, Chess, validate_basic, validate_kings};
+use movelist::{MoveList, ArrayVecExt};
+
+use option_filter::OptionFilterExt;
+
+/// A Shatranj position.
+///
+/// The roles of the standard chess pieces are reused: `Role::Queen` is the
+/// ferz, moving one square diagonally, and `Role::Bishop` is the alfil,
+/// leaping two squares diagonally. FENs, SANs and UCIs use the same letters
+/// as in standard chess.
+///
+/// Pawns can not make a double step and promote only to a ferz. There is no
+/// castling and no en passant.
+///
+/// A player wins by checkmating, stalemating or baring the opponents king.
+/// Baring is undone if the bared side can immediately capture the last
+/// remaining piece of the opponent, resulting in a draw.
+#[derive(Clone, Debug)]
+pub struct Shatranj {
+    board: Board,
+    turn: Color,
+    halfmove_clock: u32,
+    fullmoves: u32,
+}
+
+impl Default for Shatranj {
+    fn default() -> Shatranj {
+        Shatranj {
+            board: "rnbkqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBKQBNR".parse().expect("valid shatranj board"),
+            turn: White,
+            halfmove_clock: 0,
+            fullmoves: 1,
+        }
+    }
+}
+
+impl Setup for Shatranj {
+    fn board(&self) -> &Board { &self.board }
+    fn pockets(&self) -> Option<&Pockets> { None }
+    fn turn(&self) -> Color { self.turn }
+    fn castling_rights(&self) -> Bitboard { Bitboard(0) }
+    fn ep_square(&self) -> Option<Square> { None }
+    fn remaining_checks(&self) -> Option<&RemainingChecks> { None }
+    fn halfmove_clock(&self) -> u32 { self.halfmove_clock }
+    fn fullmoves(&self) -> u32 { self.fullmoves }
+}
+
+impl Position for Shatranj {
+    fn play_unchecked(&mut self, m: &Move) {
+        let color = self.turn;
+        self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+
+        if let Move::Normal { role, from, capture, to, promotion } = *m {
+            if role == Role::Pawn || capture.is_some() {
+                self.halfmove_clock = 0;
+            }
+
+            let promoted = self.board.promoted().contains(from) || promotion.is_some();
+
+            self.board.discard_piece_at(from);
+            self.board.set_piece_at(to, promotion.map_or(role.of(color), |p| p.of(color)), promoted);
+        }
+
+        if color.is_black() {
+            self.fullmoves = self.fullmoves.saturating_add(1);
+        }
+
+        self.turn = !color;
+    }
+
+    fn from_setup<S: Setup>(setup: &S) -> Result<Shatranj, PositionError> {
+        if setup.castling_rights().any() {
+            return Err(PositionError::BadCastlingRights);
+        }
+
+        if setup.ep_square().is_some() {
+            return Err(PositionError::InvalidEpSquare);
+        }
+
+        let pos = Shatranj {
+            board: setup.board().clone(),
+            turn: setup.turn(),
+            halfmove_clock: setup.halfmove_clock(),
+            fullmoves: setup.fullmoves(),
+        };
+
+        validate_basic(&pos)
+            .or_else(|| validate_kings(&pos))
+            .map_or(Ok(pos), Err)
+    }
+
+    fn king_attackers(&self, square: Square, attacker: Color, occupied: Bitboard) -> Bitboard {
+        shatranj_attacks_to(self.board(), square, attacker, occupied)
+    }
+
+    fn castling_uncovers_rank_attack(&self, _rook: Square, _king_to: Square) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, moves: &mut MoveList) {
+        let king = self.board().king_of(self.turn()).expect("king in shatranj");
+        let checkers = self.checkers();
+
+        let target = match checkers.single_square() {
+            Some(checker) if self.board().rooks().contains(checker) =>
+                attacks::between(king, checker).with(checker),
+            Some(checker) => Bitboard::from_square(checker),
+            None if checkers.any() => Bitboard(0),
+            None => !self.us(),
+        };
+
+        gen_pawn_moves(self, target, moves);
+        gen_leaper_moves(self, Role::Knight, attacks::knight_attacks, target, moves);
+        gen_leaper_moves(self, Role::Bishop, attacks::alfil_attacks, target, moves);
+        gen_leaper_moves(self, Role::Queen, attacks::ferz_attacks, target, moves);
+
+        for from in self.our(Role::Rook) {
+            for to in attacks::rook_attacks(from, self.board().occupied()) & target {
+                moves.push(Move::Normal {
+                    role: Role::Rook,
+                    from,
+                    capture: self.board().role_at(to),
+                    to,
+                    promotion: None,
+                });
+            }
+        }
+
+        let blockers = rook_blockers(self.board(), self.them(), king);
+        if blockers.any() {
+            moves.swap_retain(|m| match *m {
+                Move::Normal { from, to, .. } =>
+                    !blockers.contains(from) || attacks::aligned(from, to, king),
+                _ => true,
+            });
+        }
+
+        let occupied = self.board().occupied().without(king);
+        for to in attacks::king_attacks(king) & !self.us() {
+            if self.king_attackers(to, !self.turn(), occupied).is_empty() {
+                moves.push(Move::Normal {
+                    role: Role::King,
+                    from: king,
+                    capture: self.board().role_at(to),
+                    to,
+                    promotion: None,
+                });
+            }
+        }
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.outcome().is_some()
+    }
+
+    fn is_insufficient_material(&self) -> bool {
+        self.board().occupied() == self.board().kings()
+    }
+
+    fn is_variant_end(&self) -> bool {
+        self.bare_king_outcome().is_some()
+    }
+
+    fn variant_outcome(&self) -> Option<Outcome> {
+        self.bare_king_outcome().or_else(|| {
+            if self.checkers().is_empty() && self.legals().is_empty() {
+                Some(Outcome::Decisive { winner: !self.turn() }) // stalemate
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Shatranj {
+    fn bare_king_outcome(&self) -> Option<Outcome> {
+        let our_pieces = self.us() & !self.board().kings();
+        let their_pieces = self.them() & !self.board().kings();
+
+        if our_pieces.is_empty() && their_pieces.is_empty() {
+            Some(Outcome::Draw)
+        } else if their_pieces.is_empty() {
+            Some(Outcome::Decisive { winner: self.turn() })
+        } else if our_pieces.is_empty() {
+            let bares_back = their_pieces.single_square().map_or(false, |sq| {
+                self.legals().iter().any(|m| m.to_opt() == Some(sq))
+            });
+
+            if bares_back {
+                None
+            } else {
+                Some(Outcome::Decisive { winner: !self.turn() })
+            }
+        } else {
+            None
+        }
+    }
+}
+
+fn shatranj_attacks_to(board: &Board, sq: Square, attacker: Color, occupied: Bitboard) -> Bitboard {
+    board.by_color(attacker) & (
+        (attacks::rook_attacks(sq, occupied) & board.rooks()) |
+        (attacks::alfil_attacks(sq) & board.bishops()) |
+        (attacks::ferz_attacks(sq) & board.queens()) |
+        (attacks::knight_attacks(sq) & board.knights()) |
+        (attacks::king_attacks(sq) & board.kings()) |
+        (attacks::pawn_attacks(!attacker, sq) & board.pawns()))
+}
+
+fn rook_blockers(board: &Board, enemy: Bitboard, king: Square) -> Bitboard {
+    let snipers = attacks::rook_attacks(king, Bitboard(0)) & board.rooks() & enemy;
+
+    let mut blockers = Bitboard(0);
+
+    for sniper in snipers {
+        let b = attacks::between(king, sniper) & board.occupied();
+
+        if !b.more_than_one() {
+            blockers.add_all(b);
+        }
+    }
+
+    blockers
+}
+
+fn gen_leaper_moves<P: Position>(pos: &P, role: Role, attacks: fn(Square) -> Bitboard,
+                                 target: Bitboard, moves: &mut MoveList) {
+    for from in pos.our(role) {
+        for to in attacks(from) & target {
+            moves.push(Move::Normal {
+                role,
+                from,
+                capture: pos.board().role_at(to),
+                to,
+                promotion: None,
+            });
+        }
+    }
+}
+
+fn gen_pawn_moves(pos: &Shatranj, target: Bitboard, moves: &mut MoveList) {
+    for from in pos.our(Role::Pawn) {
+        let pushes = Bitboard::from_square(from).relative_shift(pos.turn(), 8) &
+                     !pos.board().occupied();
+
+        for to in ((attacks::pawn_attacks(pos.turn(), from) & pos.them()) | pushes) & target {
+            moves.push(Move::Normal {
+                role: Role::Pawn,
+                from,
+                capture: pos.board().role_at(to),
+                to,
+                promotion: Some(Role::Queen).filter(|_| bitboard::BACKRANKS.contains(to)),
+            });
+        }
+    }
+}
+
+/// A Hostage chess position.
+///
+/// Captured pieces go to the prison of the capturing side (see `Hostages`).
+/// Instead of a board move a player can drop a piece from their airfield,
+/// or make an exchange: release a hostage to the opponents airfield and
+/// rescue one of their own imprisoned pieces of equal or lower value,
+/// dropping it immediately (`Move::Exchange`). Pawns are worth least,
+/// knights and bishops are worth the same, and queens are worth most.
+///
+/// Pawns can not be dropped on the backranks. A pawn can only promote to a
+/// piece that is held in the opponents prison, taking its place there.
+///
+/// FENs hold the airfields and prisons in brackets after the board, separated
+/// by `|`. Prisoners are written in their own color, so in
+/// `[N|pq]` White has a knight in its airfield and holds a black pawn and a
+/// black queen hostage.
+///
+/// With full prisons on an open board there can be more legal moves than
+/// fit into a `MoveList`. `legal_moves()` then panics, and so does anything
+/// built on it, like `perft()` and `Dests`. Use `legal_moves_vec()` or
+/// `try_legal_moves()` instead. Counting, legality tests, SAN candidates and
+/// the moves from a square, captures and promotions always work.
+#[derive(Clone, Debug, Default)]
+pub struct Hostage {
+    chess: Chess,
+    hostages: Hostages,
+}
+
+impl Hostage {
+    /// The prisons and airfields of both sides.
+    pub fn hostages(&self) -> &Hostages {
+        &self.hostages
+    }
+
+    /// Collects all legal moves in an existing buffer.
+    ///
+    /// # Errors
+    ///
+    /// Errors with `TooManyMoves` if the legal moves do not fit into
+    /// `moves`. The moves that did fit are kept.
+    pub fn try_legal_moves(&self, moves: &mut MoveList) -> Result<(), TooManyMoves> {
+        self.board_moves(moves);
+        self.drop_moves(self.drop_targets(), |m| try_push(moves, m))
+    }
+
+    /// Collects all legal moves in a growable buffer, no matter how many
+    /// there are.
+    pub fn legal_moves_vec(&self) -> Vec<Move> {
+        let mut board_moves = MoveList::new();
+        self.board_moves(&mut board_moves);
+
+        let mut moves: Vec<Move> = board_moves.drain(..).collect();
+        let _ = self.drop_moves(self.drop_targets(), |m| {
+            moves.push(m);
+            Ok(())
+        });
+        moves
+    }
+
+    fn board_moves(&self, moves: &mut MoveList) {
+        self.chess.legal_moves(moves);
+
+        let their_prison = self.hostages.prisons.by_color(!self.turn());
+        moves.swap_retain(|m| m.promotion().map_or(true, |role| their_prison.by_role(role) > 0));
+    }
+
+    /// Passes the drops and exchanges to squares in `targets` to `push`,
+    /// stopping at the first error.
+    fn drop_moves<F>(&self, targets: Bitboard, mut push: F) -> Result<(), TooManyMoves>
+        where F: FnMut(Move) -> Result<(), TooManyMoves>
+    {
+        let our_prison = self.hostages.prisons.by_color(self.turn());
+        let their_prison = self.hostages.prisons.by_color(!self.turn());
+        let airfield = self.hostages.airfields.by_color(self.turn());
+
+        for role in &ROLES[..5] {
+            let to = if *role == Role::Pawn { targets & !bitboard::BACKRANKS } else { targets };
+
+            if airfield.by_role(*role) > 0 {
+                for to in to {
+                    push(Move::Put { role: *role, to })?;
+                }
+            }
+
+            if their_prison.by_role(*role) > 0 {
+                for hostage in &ROLES[..5] {
+                    if our_prison.by_role(*hostage) > 0 && hostage_value(*role) <= hostage_value(*hostage) {
+                        for to in to {
+                            push(Move::Exchange { hostage: *hostage, role: *role, to })?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn count_drops(&self, targets: Bitboard) -> usize {
+        let our_prison = self.hostages.prisons.by_color(self.turn());
+        let their_prison = self.hostages.prisons.by_color(!self.turn());
+        let airfield = self.hostages.airfields.by_color(self.turn());
+
+        ROLES[..5].iter().map(|role| {
+            let to = if *role == Role::Pawn { targets & !bitboard::BACKRANKS } else { targets };
+
+            let puts = if airfield.by_role(*role) > 0 { 1 } else { 0 };
+            let exchanges = if their_prison.by_role(*role) > 0 {
+                ROLES[..5].iter().filter(|hostage| {
+                    our_prison.by_role(**hostage) > 0 && hostage_value(*role) <= hostage_value(**hostage)
+                }).count()
+            } else {
+                0
+            };
+
+            (puts + exchanges) * to.count()
+        }).sum()
+    }
+
+    fn drop_targets(&self) -> Bitboard {
+        let checkers = self.checkers();
+
+        match checkers.single_square() {
+            Some(checker) => self.our(Role::King).first().map_or(Bitboard(0), |king| {
+                attacks::between(king, checker)
+            }),
+            None if checkers.any() => Bitboard(0),
+            None => !self.board().occupied(),
+        }
+    }
+}
+
+impl Setup for Hostage {
+    fn board(&self) -> &Board { self.chess.board() }
+    fn pockets(&self) -> Option<&Pockets> { Some(&self.hostages.airfields) }
+    fn prisons(&self) -> Option<&Pockets> { Some(&self.hostages.prisons) }
+    fn turn(&self) -> Color { self.chess.turn() }
+    fn castling_rights(&self) -> Bitboard { self.chess.castling_rights() }
+    fn ep_square(&self) -> Option<Square> { self.chess.ep_square() }
+    fn remaining_checks(&self) -> Option<&RemainingChecks> { None }
+    fn halfmove_clock(&self) -> u32 { self.chess.halfmove_clock() }
+    fn fullmoves(&self) -> u32 { self.chess.fullmoves() }
+}
+
+impl Position for Hostage {
+    fn play_unchecked(&mut self, m: &Move) {
+        let turn = self.turn();
+
+        match *m {
+            Move::Normal { capture, promotion, .. } => {
+                if let Some(role) = capture {
+                    self.hostages.capture(turn, role);
+                }
+                if let Some(role) = promotion {
+                    self.hostages.promote(turn, role);
+                }
+            },
+            Move::EnPassant { .. } => self.hostages.capture(turn, Role::Pawn),
+            Move::Put { role, .. } => self.hostages.airfields.remove(role.of(turn)),
+            Move::Exchange { hostage, role, .. } => self.hostages.exchange(turn, hostage, role),
+            Move::Castle { .. } | Move::Null => (),
+        }
+
+        self.chess.play_unchecked(m);
+    }
+
+    fn from_setup<S: Setup>(setup: &S) -> Result<Hostage, PositionError> {
+        let chess = Chess::from_setup(setup)?;
+
+        let hostages = Hostages {
+            prisons: setup.prisons().cloned().unwrap_or_default(),
+            airfields: setup.pockets().cloned().unwrap_or_default(),
+        };
+
+        for color in &[White, Black] {
+            let airfield = hostages.airfields.by_color(*color);
+            let prisoners = hostages.prisons.by_color(!*color);
+
+            if airfield.kings > 0 || prisoners.kings > 0 {
+                return Err(PositionError::TooManyKings);
+            }
+
+            let board = chess.board();
+            if board.by_color(*color).count() + airfield.count() as usize + prisoners.count() as usize > 16 {
+                return Err(PositionError::TooManyPieces);
+            }
+            if board.by_piece(color.pawn()).count() + airfield.pawns as usize + prisoners.pawns as usize > 8 {
+                return Err(PositionError::TooManyPawns);
+            }
+        }
+
+        Ok(Hostage { chess, hostages })
+    }
+
+    fn castling_uncovers_rank_attack(&self, rook: Square, king_to: Square) -> bool {
+        self.chess.castling_uncovers_rank_attack(rook, king_to)
+    }
+
+    fn legal_moves(&self, moves: &mut MoveList) {
+        self.try_legal_moves(moves).expect("legal moves fit into MoveList, use legal_moves_vec() otherwise");
+    }
+
+    fn target_moves(&self, target: Bitboard, moves: &mut MoveList) {
+        self.board_moves(moves);
+        moves.swap_retain(|m| m.to_opt().map_or(false, |to| target.contains(to)));
+        self.drop_moves(self.drop_targets() & target, |m| try_push(moves, m))
+            .expect("legal moves fit into MoveList, use legal_moves_vec() otherwise");
+    }
+
+    fn legal_moves_from(&self, from: Square, moves: &mut MoveList) {
+        self.board_moves(moves);
+        moves.swap_retain(|m| m.from() == Some(from));
+    }
+
+    fn capture_moves(&self, moves: &mut MoveList) {
+        self.board_moves(moves);
+        moves.swap_retain(|m| m.capture().is_some());
+    }
+
+    fn quiet_moves(&self, moves: &mut MoveList) {
+        self.board_moves(moves);
+        moves.swap_retain(|m| m.capture().is_none());
+        self.drop_moves(self.drop_targets(), |m| try_push(moves, m))
+            .expect("legal moves fit into MoveList, use legal_moves_vec() otherwise");
+    }
+
+    fn promotion_moves(&self, moves: &mut MoveList) {
+        self.board_moves(moves);
+        moves.swap_retain(|m| m.promotion().is_some());
+    }
+
+    fn check_moves(&self, moves: &mut MoveList) {
+        self.board_moves(moves);
+        moves.swap_retain(|m| self.gives_check(m));
+        self.drop_moves(self.drop_targets(), |m| {
+            if self.gives_check(&m) { try_push(moves, m) } else { Ok(()) }
+        }).expect("checks fit into MoveList");
+    }
+
+    fn count_legal_moves(&self) -> usize {
+        let mut moves = MoveList::new();
+        self.board_moves(&mut moves);
+        moves.len() + self.count_drops(self.drop_targets())
+    }
+
+    fn san_candidates(&self, role: Role, to: Square, moves: &mut MoveList) {
+        self.chess.san_candidates(role, to, moves);
+
+        let their_prison = self.hostages.prisons.by_color(!self.turn());
+        moves.swap_retain(|m| m.promotion().map_or(true, |role| their_prison.by_role(role) > 0));
+
+        let targets = self.drop_targets() & Bitboard::from_square(to);
+        self.drop_moves(targets, |m| try_push(moves, m)).expect("drops to a single square fit");
+        moves.swap_retain(|m| match *m {
+            Move::Put { role: r, .. } => r == role,
+            Move::Exchange { .. } => false,
+            _ => true,
+        });
+    }
+
+    fn is_legal(&self, m: &Move) -> bool {
+        let mut moves = MoveList::new();
+        match *m {
+            Move::Put { to, .. } | Move::Exchange { to, .. } => {
+                let targets = self.drop_targets() & Bitboard::from_square(to);
+                self.drop_moves(targets, |m| try_push(&mut moves, m)).expect("drops to a single square fit");
+                moves.contains(m)
+            },
+            _ => {
+                let their_prison = self.hostages.prisons.by_color(!self.turn());
+                self.chess.is_legal(m) &&
+                m.promotion().map_or(true, |role| their_prison.by_role(role) > 0)
+            },
+        }
+    }
+
+    fn castling_moves(&self, side: CastlingSide, moves: &mut MoveList) {
+        self.chess.castling_moves(side, moves);
+    }
+
+    fn see(&self, m: &Move) -> Option<i32> {
+        self.chess.see(m)
+    }
+
+    fn see_ge(&self, m: &Move, threshold: i32) -> Option<bool> {
+        self.chess.see_ge(m, threshold)
+    }
+
+    fn is_insufficient_material(&self) -> bool {
+        self.board().occupied() == self.board().kings() && self.hostages.count() == 0
+    }
+
+    fn is_variant_end(&self) -> bool { false }
+    fn variant_outcome(&self) -> Option<Outcome> { None }
+}
+
+/// Error when the legal moves of a position do not fit into a `MoveList`.
+pub struct TooManyMoves {
+    _priv: (),
+}
+
+impl fmt::Debug for TooManyMoves {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TooManyMoves").finish()
+    }
+}
+
+impl fmt::Display for TooManyMoves {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "too many legal moves".fmt(f)
+    }
+}
+
+impl Error for TooManyMoves {
+    fn description(&self) -> &str {
+        "too many legal moves"
+    }
+}
+
+fn try_push(moves: &mut MoveList, m: Move) -> Result<(), TooManyMoves> {
+    moves.try_push(m).map_err(|_| TooManyMoves { _priv: () })
+}
+
+fn hostage_value(role: Role) -> u8 {
+    match role {
+        Role::Pawn => 1,
+        Role::Knight | Role::Bishop => 2,
+        Role::Rook => 3,
+        Role::Queen => 4,
+        Role::King => 5,
+    }
+}
+
+/// Fairy pieces assigned to the six roles, with their FEN letters.
+///
+/// The `Role::King` piece is royal. Pawns promote on the backrank to any
+/// piece other than pawns and kings.
+#[derive(Clone, Debug)]
+pub struct PieceSet {
+    pieces: [Betza; 6],
+    chars: [char; 6],
+}
+
+impl PieceSet {
+    /// The standard chess pieces. Note that `mfWcfF` pawns have no double
+    /// step.
+    pub fn new() -> PieceSet {
+        PieceSet {
+            pieces: [
+                "mfWcfF".parse().expect("valid pawn"),
+                "N".parse().expect("valid knight"),
+                "B".parse().expect("valid bishop"),
+                "R".parse().expect("valid rook"),
+                "Q".parse().expect("valid queen"),
+                "K".parse().expect("valid king"),
+            ],
+            chars: ['p', 'n', 'b', 'r', 'q', 'k'],
+        }
+    }
+
+    /// Assigns a piece and its (lowercase) FEN letter to `role`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `ch` is not an ASCII letter or is already used by another
+    /// role.
+    pub fn set(&mut self, role: Role, ch: char, piece: Betza) -> Result<&mut PieceSet, InvalidPieceChar> {
+        let ch = ch.to_ascii_lowercase();
+
+        if !ch.is_ascii_lowercase() || self.chars.iter().zip(&ROLES).any(|(c, r)| *c == ch && *r != role) {
+            return Err(InvalidPieceChar { _priv: () });
+        }
+
+        self.pieces[role as usize] = piece;
+        self.chars[role as usize] = ch;
+        Ok(self)
+    }
+
+    /// The piece assigned to `role`.
+    pub fn betza(&self, role: Role) -> &Betza {
+        &self.pieces[role as usize]
+    }
+
+    /// The FEN letter of `piece`, uppercase for White.
+    pub fn char(&self, piece: Piece) -> char {
+        let ch = self.chars[piece.role as usize];
+        piece.color.fold(ch.to_ascii_uppercase(), ch)
+    }
+
+    /// Looks up the piece with the FEN letter `ch`, if any.
+    pub fn piece_from_char(&self, ch: char) -> Option<Piece> {
+        self.chars.iter().position(|c| *c == ch.to_ascii_lowercase()).map(|idx| {
+            ROLES[idx].of(Color::from_bool(ch == ch.to_ascii_uppercase()))
+        })
+    }
+
+    /// Parses a board FEN using the letters of this piece set.
+    ///
+    /// # Errors
+    ///
+    /// Errors with `FenError::InvalidBoard` if the board FEN is invalid or
+    /// contains letters that are not in this piece set, including standard
+    /// letters that have been reassigned.
+    pub fn parse_board(&self, board_fen: &str) -> Result<Board, FenError> {
+        let standard = board_fen.chars().map(|ch| {
+            match self.piece_from_char(ch) {
+                Some(piece) => Ok(piece.char()),
+                None if ch.is_ascii_alphabetic() => Err(FenError::InvalidBoard),
+                None => Ok(ch),
+            }
+        }).collect::<Result<String, FenError>>()?;
+
+        standard.parse()
+    }
+
+    /// Creates a board FEN using the letters of this piece set.
+    pub fn board_fen(&self, board: &Board, opts: &FenOpts) -> String {
+        fen::board_fen(board, opts).chars().map(|ch| {
+            Piece::from_char(ch).map_or(ch, |piece| self.char(piece))
+        }).collect()
+    }
+
+    /// Squares attacked by `piece` on `sq` with `occupied` squares.
+    pub fn attacks(&self, sq: Square, piece: Piece, occupied: Bitboard) -> Bitboard {
+        self.betza(piece.role).attacks(piece.color, sq, occupied)
+    }
+}
+
+impl Default for PieceSet {
+    fn default() -> PieceSet {
+        PieceSet::new()
+    }
+}
+
+/// Error when assigning a FEN letter that is invalid or already in use to a
+/// role of a `PieceSet`.
+pub struct InvalidPieceChar {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidPieceChar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidPieceChar").finish()
+    }
+}
+
+impl fmt::Display for InvalidPieceChar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid piece letter".fmt(f)
+    }
+}
+
+impl Error for InvalidPieceChar {
+    fn description(&self) -> &str {
+        "invalid piece letter"
+    }
+}
+
+/// A position with fairy pieces defined by a `PieceSet`.
+///
+/// There is no castling and no en passant. Positions created with
+/// `from_setup()` use the standard `PieceSet`.
+///
+/// Strong pieces can have more legal moves than fit into a `MoveList`.
+/// `legal_moves()` then panics. Use `try_legal_moves()` or
+/// `legal_moves_vec()` instead. `count_legal_moves()` is always exact.
+#[derive(Clone, Debug)]
+pub struct Fairy {
+    pieces: Arc<PieceSet>,
+    board: Board,
+    turn: Color,
+    halfmove_clock: u32,
+    fullmoves: u32,
+}
+
+impl Fairy {
+    /// Sets up a position with the given pieces.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the position is not legal.
+    pub fn new(pieces: Arc<PieceSet>, board: Board, turn: Color) -> Result<Fairy, PositionError> {
+        let pos = Fairy { pieces, board, turn, halfmove_clock: 0, fullmoves: 1 };
+
+        validate_basic(&pos)
+            .or_else(|| validate_kings(&pos))
+            .map_or(Ok(pos), Err)
+    }
+
+    /// The pieces used in this position.
+    pub fn pieces(&self) -> &PieceSet {
+        &self.pieces
+    }
+
+    /// Collects all legal moves in an existing buffer.
+    ///
+    /// # Errors
+    ///
+    /// Errors with `TooManyMoves` if the legal moves do not fit into
+    /// `moves`. The moves that did fit are kept.
+    pub fn try_legal_moves(&self, moves: &mut MoveList) -> Result<(), TooManyMoves> {
+        self.gen_legal(|m| try_push(moves, m))
+    }
+
+    /// Collects all legal moves in a growable buffer, no matter how many
+    /// there are.
+    pub fn legal_moves_vec(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let _ = self.gen_legal(|m| {
+            moves.push(m);
+            Ok(())
+        });
+        moves
+    }
+
+    /// Passes the legal moves to `push`, stopping at the first error.
+    fn gen_legal<F>(&self, mut push: F) -> Result<(), TooManyMoves>
+        where F: FnMut(Move) -> Result<(), TooManyMoves>
+    {
+        let occupied = self.board().occupied();
+
+        for from in self.us() {
+            let role = self.board().role_at(from).expect("piece on our square");
+            let betza = self.pieces.betza(role);
+
+            let targets = (betza.moves(self.turn(), from, occupied) & !occupied) |
+                          (betza.attacks(self.turn(), from, occupied) & self.them());
+
+            for to in targets {
+                let capture = self.board().role_at(to);
+
+                if !self.is_safe(&Move::Normal { role, from, capture, to, promotion: None }) {
+                    continue;
+                }
+
+                if role == Role::Pawn && Bitboard::relative_rank(self.turn(), 7).contains(to) {
+                    for promotion in &ROLES[1..5] {
+                        push(Move::Normal { role, from, capture, to, promotion: Some(*promotion) })?;
+                    }
+                } else {
+                    push(Move::Normal { role, from, capture, to, promotion: None })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn attackers(&self, board: &Board, sq: Square, attacker: Color, occupied: Bitboard) -> Bitboard {
+        (board.by_color(attacker) & occupied).filter(|from| {
+            board.piece_at(*from).map_or(false, |piece| {
+                self.pieces.attacks(*from, piece, occupied).contains(sq)
+            })
+        }).collect()
+    }
+
+    fn is_safe(&self, m: &Move) -> bool {
+        let mut board = self.board.clone();
+        if let Move::Normal { role, from, to, promotion, .. } = *m {
+            board.discard_piece_at(from);
+            board.set_piece_at(to, promotion.unwrap_or(role).of(self.turn), false);
+        }
+
+        board.king_of(self.turn).map_or(true, |king| {
+            self.attackers(&board, king, !self.turn, board.occupied()).is_empty()
+        })
+    }
+}
+
+impl Setup for Fairy {
+    fn board(&self) -> &Board { &self.board }
+    fn pockets(&self) -> Option<&Pockets> { None }
+    fn turn(&self) -> Color { self.turn }
+    fn castling_rights(&self) -> Bitboard { Bitboard(0) }
+    fn ep_square(&self) -> Option<Square> { None }
+    fn remaining_checks(&self) -> Option<&RemainingChecks> { None }
+    fn halfmove_clock(&self) -> u32 { self.halfmove_clock }
+    fn fullmoves(&self) -> u32 { self.fullmoves }
+}
+
+impl Position for Fairy {
+    fn play_unchecked(&mut self, m: &Move) {
+        let color = self.turn;
+        self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+
+        if let Move::Normal { role, from, capture, to, promotion } = *m {
+            if role == Role::Pawn || capture.is_some() {
+                self.halfmove_clock = 0;
+            }
+
+            let promoted = self.board.promoted().contains(from) || promotion.is_some();
+
+            self.board.discard_piece_at(from);
+            self.board.set_piece_at(to, promotion.map_or(role.of(color), |p| p.of(color)), promoted);
+        }
+
+        if color == Black {
+            self.fullmoves = self.fullmoves.saturating_add(1);
+        }
+
+        self.turn = !color;
+    }
+
+    fn from_setup<S: Setup>(setup: &S) -> Result<Fairy, PositionError> {
+        if setup.castling_rights().any() {
+            return Err(PositionError::BadCastlingRights);
+        }
+
+        if setup.ep_square().is_some() {
+            return Err(PositionError::InvalidEpSquare);
+        }
+
+        let mut pos = Fairy::new(Arc::new(PieceSet::default()), setup.board().clone(), setup.turn())?;
+        pos.halfmove_clock = setup.halfmove_clock();
+        pos.fullmoves = setup.fullmoves();
+        Ok(pos)
+    }
+
+    fn king_attackers(&self, square: Square, attacker: Color, occupied: Bitboard) -> Bitboard {
+        self.attackers(self.board(), square, attacker, occupied)
+    }
+
+    fn castling_uncovers_rank_attack(&self, _rook: Square, _king_to: Square) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, moves: &mut MoveList) {
+        self.try_legal_moves(moves).expect("legal moves fit into MoveList, use legal_moves_vec() otherwise");
+    }
+
+    fn count_legal_moves(&self) -> usize {
+        let mut count = 0;
+        let _ = self.gen_legal(|_| {
+            count += 1;
+            Ok(())
+        });
+        count
+    }
+
+    fn is_insufficient_material(&self) -> bool {
+        self.board().occupied() == self.board().kings()
+    }
+
+    fn is_variant_end(&self) -> bool { false }
+    fn variant_outcome(&self) -> Option<Outcome> { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fen::Fen;
+    use perft::perft;
+    use types::Black;
+    use square;
+    use uci::Uci;
+    use position::IllegalMoveReason;
+
+    fn setup(fen: &str) -> Shatranj {
+        fen.parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("legal position")
+    }
+
+    #[test]
+    fn test_shatranj_perft() {
+        let pos = Shatranj::default();
+        assert_eq!(perft(&pos, 1), 16);
+        assert_eq!(perft(&pos, 2), 256);
+    }
+
+    #[test]
+    fn test_shatranj_promotion() {
+        let pos = setup("3k4/P7/8/8/8/8/8/4K3 w - - 0 1");
+        let legals = pos.legals();
+        assert!(legals.iter().all(|m| m.promotion().map_or(true, |p| p == Role::Queen)));
+        assert!(legals.iter().any(|m| m.promotion() == Some(Role::Queen)));
+
+        let m = "a7a8r".parse::<Uci>().expect("valid uci");
+        assert_eq!(m.to_move(&pos).expect_err("illegal move").reason(), IllegalMoveReason::VariantRule);
+    }
+
+    #[test]
+    fn test_shatranj_illegal_move_reason() {
+        let pos = Shatranj::default();
+        let m = "e2e4".parse::<Uci>().expect("valid uci");
+        assert_eq!(m.to_move(&pos).expect_err("illegal move").reason(), IllegalMoveReason::VariantRule);
+    }
+
+    #[test]
+    fn test_shatranj_bare_king() {
+        let pos = setup("3k4/8/8/8/8/8/8/R3K3 b - - 0 1");
+        assert_eq!(pos.outcome(), Some(Outcome::Decisive { winner: White }));
+
+        // Black can bare the white king in return.
+        let pos = setup("3k4/3R4/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(pos.outcome(), None);
+    }
+
+    #[test]
+    fn test_shatranj_stalemate() {
+        let pos = setup("k7/2K5/1Q6/7p/7P/8/8/8 b - - 0 1");
+        assert!(pos.is_stalemate());
+        assert_eq!(pos.outcome(), Some(Outcome::Decisive { winner: White }));
+
+        let pos = setup("K7/2k5/1q6/7p/7P/8/8/8 w - - 0 1");
+        assert_eq!(pos.outcome(), Some(Outcome::Decisive { winner: Black }));
+    }
+
+    #[test]
+    fn test_hostage_exchange() {
+        let mut pos = Hostage::default();
+        for uci in &["e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a2", "a1a2"] {
+            let m = uci.parse::<Uci>().expect("valid uci").to_move(&pos).expect("legal uci");
+            pos.play_unchecked(&m);
+        }
+
+        assert_eq!(pos.hostages().prisons.white.queens, 1);
+        assert_eq!(pos.hostages().prisons.black.pawns, 2);
+
+        // Black can not rescue the queen with a pawn, but can rescue pawns.
+        let legals = pos.legals();
+        assert!(legals.iter().all(|m| match *m {
+            Move::Exchange { role, .. } => role == Role::Pawn,
+            _ => true,
+        }));
+
+        let m = Move::Exchange { hostage: Role::Pawn, role: Role::Pawn, to: square::E6 };
+        assert!(legals.contains(&m));
+
+        pos.play_unchecked(&m);
+        assert_eq!(pos.board().piece_at(square::E6), Some(Black.pawn()));
+        assert_eq!(pos.hostages().prisons.black.pawns, 1);
+        assert_eq!(pos.hostages().prisons.white.pawns, 0);
+        assert_eq!(pos.hostages().airfields.white.pawns, 1);
+        assert_eq!(pos.pockets().map(|p| p.to_string()), Some("P".to_owned()));
+    }
+
+    #[test]
+    fn test_hostage_fen() {
+        let fen: Fen = "4k3/8/8/8/8/8/8/4K3[N|pq] w - - 0 1".parse().expect("valid fen");
+        let pos: Hostage = fen.position().expect("legal position");
+        assert_eq!(pos.hostages().airfields.white.knights, 1);
+        assert_eq!(pos.hostages().prisons.white.pawns, 1);
+        assert_eq!(pos.hostages().prisons.white.queens, 1);
+        assert_eq!(fen::fen(&pos, &FenOpts::default()), "4k3/8/8/8/8/8/8/4K3[N|pq] w - - 0 1");
+
+        // Pieces in airfields and prisons count towards the material limit.
+        let fen: Fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[n] w KQkq - 0 1".parse().expect("valid fen");
+        assert!(match fen.position::<Hostage>() { Err(PositionError::TooManyPieces) => true, _ => false });
+        let fen: Fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[|P] w KQkq - 0 1".parse().expect("valid fen");
+        assert!(match fen.position::<Hostage>() { Err(PositionError::TooManyPieces) => true, _ => false });
+        let fen: Fen = "4k3/pppppppp/8/8/8/8/8/4K3[p] w - - 0 1".parse().expect("valid fen");
+        assert!(match fen.position::<Hostage>() { Err(PositionError::TooManyPawns) => true, _ => false });
+    }
+
+    #[test]
+    fn test_hostage_too_many_moves() {
+        let pos: Hostage = "4k3/8/8/8/8/8/8/4K3[PNBRQ|pnbrqPNBRQ] w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("legal position");
+
+        let mut moves = MoveList::new();
+        assert!(pos.try_legal_moves(&mut moves).is_err());
+        assert_eq!(moves.len(), moves.capacity());
+        assert_eq!(pos.count_legal_moves(), 1223);
+
+        let m = Move::Exchange { hostage: Role::Queen, role: Role::Queen, to: square::H8 };
+        assert!(!moves.contains(&m));
+        assert!(pos.is_legal(&m));
+        assert!(!pos.is_legal(&Move::Exchange { hostage: Role::Pawn, role: Role::Queen, to: square::H8 }));
+
+        let all = pos.legal_moves_vec();
+        assert_eq!(all.len(), 1223);
+        assert!(all.contains(&m));
+
+        let mut moves = MoveList::new();
+        pos.capture_moves(&mut moves);
+        assert!(moves.is_empty());
+        pos.legal_moves_from(square::E1, &mut moves);
+        assert_eq!(moves.len(), 5);
+        moves.clear();
+        pos.target_moves(Bitboard::from_square(square::H8), &mut moves);
+        assert_eq!(moves.len(), 15);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hostage_legal_moves_overflow() {
+        let pos: Hostage = "4k3/8/8/8/8/8/8/4K3[PNBRQ|pnbrqPNBRQ] w - - 0 1".parse::<Fen>()
+            .expect("valid fen")
+            .position()
+            .expect("legal position");
+
+        pos.legals();
+    }
+
+    #[test]
+    fn test_fairy_standard_pieces() {
+        let fen: Fen = "r3k3/8/3n4/8/8/2B5/6q1/R2QK3 w - - 0 1".parse().expect("valid fen");
+        let chess: Chess = fen.position().expect("legal position");
+        let fairy: Fairy = fen.position().expect("legal position");
+        assert_eq!(perft(&fairy, 3), perft(&chess, 3));
+    }
+
+    #[test]
+    fn test_fairy_letters() {
+        let mut pieces = PieceSet::default();
+        pieces.set(Role::Knight, 'c', "NR".parse().expect("valid betza")).expect("unused letter");
+        assert!(pieces.set(Role::Bishop, 'C', "B".parse().expect("valid betza")).is_err());
+        assert!(pieces.set(Role::Bishop, '1', "B".parse().expect("valid betza")).is_err());
+        assert!(pieces.set(Role::Knight, 'c', "N".parse().expect("valid betza")).is_ok());
+
+        let board = pieces.parse_board("4k3/8/8/8/8/8/8/2C1K3").expect("valid board");
+        assert!(pieces.parse_board("4k3/8/8/8/8/8/8/2N1K3").is_err());
+        assert_eq!(board.piece_at(square::C1), Some(White.knight()));
+        assert_eq!(pieces.board_fen(&board, &FenOpts::default()), "4k3/8/8/8/8/8/8/2C1K3");
+
+        let pos = Fairy::new(Arc::new(pieces), board, Black).expect("legal position");
+        assert_eq!(pos.checkers(), Bitboard(0));
+        assert_eq!(pos.legals().len(), 5);
+    }
+
+    #[test]
+    fn test_fairy_promotion_rank() {
+        // Pawns that can move backwards do not promote on their own backrank.
+        let mut pieces = PieceSet::default();
+        pieces.set(Role::Pawn, 'p', "mvW".parse().expect("valid betza")).expect("unused letter");
+
+        let board = pieces.parse_board("4k3/P7/8/8/8/8/1P6/4K3").expect("valid board");
+        let pos = Fairy::new(Arc::new(pieces), board, White).expect("legal position");
+
+        let mut moves = MoveList::new();
+        pos.legal_moves_from(square::B2, &mut moves);
+        assert_eq!(moves.len(), 2);
+        assert!(moves.iter().all(|m| m.promotion().is_none()));
+
+        moves.clear();
+        pos.legal_moves_from(square::A7, &mut moves);
+        assert_eq!(moves.len(), 5);
+        assert_eq!(moves.iter().filter(|m| m.promotion().is_some()).count(), 4);
+    }
+
+    #[test]
+    fn test_fairy_too_many_moves() {
+        // Pawns and queens that also leap to any square within a distance of
+        // three, but never capture or give check.
+        let mut pieces = PieceSet::default();
+        pieces.set(Role::Pawn, 'p', "mQmDmNmAmHmCmZmG".parse().expect("valid betza")).expect("unused letter");
+        pieces.set(Role::Queen, 'q', "mQmDmNmAmHmCmZmG".parse().expect("valid betza")).expect("unused letter");
+
+        let board = pieces.parse_board("8/P2P2P1/1P2P2P/Q2P1P1Q/3Q4/2Q2Q2/Q6Q/k3K3").expect("valid board");
+        let pos = Fairy::new(Arc::new(pieces), board, White).expect("legal position");
+
+        let count = pos.count_legal_moves();
+        assert!(count > MoveList::new().capacity());
+        assert_eq!(pos.legal_moves_vec().len(), count);
+
+        let mut moves = MoveList::new();
+        assert!(pos.try_legal_moves(&mut moves).is_err());
+        assert_eq!(moves.len(), moves.capacity());
+    }
+}